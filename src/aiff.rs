@@ -0,0 +1,231 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{
+    sample::{Encoding, Endianness, SampleFormat},
+    signal::{FileFormat, Metadata, SignalReadError, SignalWriteError},
+};
+
+/// The version of the AIFF-C specification, in the `FVER` chunk.
+const AIFC_VERSION: u32 = 0xA280_5140;
+
+/// The format of an AIFF or AIFF-C file, read from its `COMM` chunk.
+#[derive(Debug, Clone)]
+pub(crate) struct AiffHeader {
+    pub channels: u16,
+    pub frames: u32,
+    pub sample_rate: u32,
+    /// The significant bits of every sample, which may be fewer than `encoding` stores.
+    pub bits_per_sample: u16,
+    pub encoding: Encoding,
+    pub endianness: Endianness,
+    pub metadata: Metadata,
+}
+
+/// The tags of a signal with the identifiers of the AIFF text chunks storing them. Only the
+/// title, artist and comment have one.
+fn tags(metadata: &Metadata) -> [(&'static [u8; 4], &Option<String>); 3] {
+    [
+        (b"NAME", &metadata.title),
+        (b"AUTH", &metadata.artist),
+        (b"ANNO", &metadata.comment),
+    ]
+}
+
+/// Read the chunks of an AIFF or AIFF-C file, leaving `reader` at the start of its samples.
+pub(crate) fn read_header<R: Read + Seek>(reader: &mut R) -> Result<AiffHeader, SignalReadError> {
+    let mut form = [0; 12];
+    reader.read_exact(&mut form)?;
+    let aifc = match &form[8..12] {
+        _ if &form[0..4] != b"FORM" => return Err(SignalReadError::Aiff("no FORM chunk")),
+        b"AIFF" => false,
+        b"AIFC" => true,
+        _ => return Err(SignalReadError::Aiff("not an AIFF or AIFF-C file")),
+    };
+
+    let mut header = None;
+    let mut data_start = None;
+    let mut metadata = Metadata::default();
+
+    let mut chunk = [0; 8];
+    while reader.read_exact(&mut chunk).is_ok() {
+        let len = u32::from_be_bytes(chunk[4..8].try_into().unwrap()) as u64;
+        let start = reader.stream_position()?;
+
+        match &chunk[0..4] {
+            b"COMM" => {
+                header = Some(parse_comm(&read_chunk(reader, len)?, aifc)?);
+            },
+            b"SSND" => {
+                let mut offset = [0; 4];
+                reader.read_exact(&mut offset)?;
+                data_start = Some(start + 8 + u32::from_be_bytes(offset) as u64);
+            },
+            id @ (b"NAME" | b"AUTH" | b"ANNO") => {
+                let text = read_chunk(reader, len)?;
+                let text = String::from_utf8_lossy(text.split(|&b| b == 0).next().unwrap_or_default()).into_owned();
+
+                match id {
+                    b"NAME" => metadata.title = Some(text),
+                    b"AUTH" => metadata.artist = Some(text),
+                    _ => metadata.comment = Some(text),
+                }
+            },
+            _ => {},
+        }
+
+        reader.seek(SeekFrom::Start(start + len + len % 2))?;
+    }
+
+    let header = header.ok_or(SignalReadError::Aiff("no COMM chunk"))?;
+    let data_start = data_start.ok_or(SignalReadError::Aiff("no SSND chunk"))?;
+    reader.seek(SeekFrom::Start(data_start))?;
+
+    Ok(AiffHeader { metadata, ..header })
+}
+
+/// Read the `len` bytes of a chunk's body, growing the buffer only as far as the file goes, so
+/// that a corrupt length cannot force a huge allocation.
+fn read_chunk<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>, SignalReadError> {
+    let mut body = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut body)?;
+
+    if (body.len() as u64) < len {
+        return Err(SignalReadError::Aiff("a chunk extends past the end of the file"));
+    }
+
+    Ok(body)
+}
+
+/// Parse a `COMM` chunk, whose AIFF-C form adds the compression type.
+fn parse_comm(comm: &[u8], aifc: bool) -> Result<AiffHeader, SignalReadError> {
+    if comm.len() < if aifc { 22 } else { 18 } {
+        return Err(SignalReadError::Aiff("COMM chunk too short"));
+    }
+
+    let channels = u16::from_be_bytes([comm[0], comm[1]]);
+    if channels == 0 {
+        return Err(SignalReadError::NoChannels);
+    }
+    let frames = u32::from_be_bytes(comm[2..6].try_into().unwrap());
+    let bits_per_sample = u16::from_be_bytes([comm[6], comm[7]]);
+    let sample_rate = from_extended(comm[8..18].try_into().unwrap());
+
+    let compression = if aifc { &comm[18..22] } else { b"NONE" };
+    let (encoding, endianness) = match compression {
+        b"NONE" | b"twos" => (Encoding::holding(SampleFormat::Int, bits_per_sample), Endianness::Big),
+        b"sowt" => (Encoding::holding(SampleFormat::Int, bits_per_sample), Endianness::Little),
+        b"fl32" | b"FL32" => (Some(Encoding::Float32), Endianness::Big),
+        b"fl64" | b"FL64" => (Some(Encoding::Float64), Endianness::Big),
+        _ => return Err(SignalReadError::UnsupportedCodec(String::from_utf8_lossy(compression).trim().to_string())),
+    };
+    let encoding = encoding.ok_or(SignalReadError::Aiff("unsupported sample size"))?;
+
+    Ok(AiffHeader {
+        channels,
+        frames,
+        sample_rate,
+        bits_per_sample: bits_per_sample.min(encoding.bits_per_sample()),
+        encoding,
+        endianness,
+        metadata: Metadata::default(),
+    })
+}
+
+/// The header of an AIFF file, or an AIFF-C file for floating-point samples, up to the start
+/// of the samples of its `SSND` chunk, which fails if the chunk sizes don't fit in 32 bits.
+pub(crate) fn header(
+    channels: u16,
+    frames: usize,
+    sample_rate: u32,
+    encoding: Encoding,
+    metadata: &Metadata,
+) -> Result<Vec<u8>, SignalWriteError> {
+    let aifc = encoding.sample_format() == SampleFormat::Float;
+    let too_long = || SignalWriteError::TooLong { format: FileFormat::Aiff, frames };
+    let frame_count = u32::try_from(frames).map_err(|_| too_long())?;
+
+    let mut comm = Vec::with_capacity(40);
+    comm.extend_from_slice(&channels.to_be_bytes());
+    comm.extend_from_slice(&frame_count.to_be_bytes());
+    comm.extend_from_slice(&encoding.bits_per_sample().to_be_bytes());
+    comm.extend_from_slice(&to_extended(sample_rate));
+    if aifc {
+        let (id, name): (&[u8; 4], &str) = match encoding {
+            Encoding::Float32 => (b"fl32", "IEEE 32-bit float"),
+            _ => (b"fl64", "IEEE 64-bit float"),
+        };
+
+        // A Pascal string, padded to an even length.
+        comm.extend_from_slice(id);
+        comm.push(name.len() as u8);
+        comm.extend_from_slice(name.as_bytes());
+        if name.len() % 2 == 0 {
+            comm.push(0);
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut chunk = |id: &[u8; 4], body: &[u8]| {
+        chunks.extend_from_slice(id);
+        chunks.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        chunks.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunks.push(0);
+        }
+    };
+
+    if aifc {
+        chunk(b"FVER", &AIFC_VERSION.to_be_bytes());
+    }
+    chunk(b"COMM", &comm);
+    for (id, value) in tags(metadata) {
+        if let Some(value) = value {
+            chunk(id, value.as_bytes());
+        }
+    }
+
+    let data_len = frames
+        .checked_mul(channels as usize * encoding.bytes_per_sample())
+        .ok_or_else(too_long)?;
+    let ssnd_len = data_len.checked_add(8).and_then(|len| u32::try_from(len).ok()).ok_or_else(too_long)?;
+    let form_len = (4 + chunks.len() + 8 + data_len % 2)
+        .checked_add(ssnd_len as usize)
+        .and_then(|len| u32::try_from(len).ok())
+        .ok_or_else(too_long)?;
+
+    let mut header = Vec::with_capacity(12 + chunks.len() + 16);
+    header.extend_from_slice(b"FORM");
+    header.extend_from_slice(&form_len.to_be_bytes());
+    header.extend_from_slice(if aifc { b"AIFC" } else { b"AIFF" });
+    header.extend_from_slice(&chunks);
+    header.extend_from_slice(b"SSND");
+    header.extend_from_slice(&ssnd_len.to_be_bytes());
+    // Neither an offset nor a block size.
+    header.extend_from_slice(&[0; 8]);
+    Ok(header)
+}
+
+/// Decode the sample rate, an 80-bit IEEE 754 extended-precision float.
+fn from_extended(bytes: [u8; 10]) -> u32 {
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7FFF) as i32 - 16383;
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+
+    if bytes[0] & 0x80 != 0 || !(0..32).contains(&exponent) {
+        return 0;
+    }
+
+    (mantissa as f64 * 2f64.powi(exponent - 63)).round() as u32
+}
+
+/// Encode the sample rate as an 80-bit IEEE 754 extended-precision float.
+fn to_extended(sample_rate: u32) -> [u8; 10] {
+    let mut bytes = [0; 10];
+    if sample_rate == 0 {
+        return bytes;
+    }
+
+    let exponent = 31 - sample_rate.leading_zeros();
+    bytes[0..2].copy_from_slice(&((16383 + exponent) as u16).to_be_bytes());
+    bytes[2..10].copy_from_slice(&((sample_rate as u64) << (63 - exponent)).to_be_bytes());
+    bytes
+}
@@ -0,0 +1,330 @@
+use ndarray::{s, Array1, Array2, ArrayView1, Axis};
+use num_complex::{Complex, ComplexFloat};
+
+use crate::{
+    fft::{stft, FftPlan},
+    progress::Monitor,
+    signal::TimeDomainSignal,
+    stretcher::{check_input, FrameConfig, StretchConfig, StretchError},
+    windows::{build_window, hann_window, WindowFn},
+};
+
+/// A summary of the short-time spectrum of a signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectralSummary {
+    /// The number of STFT frames.
+    pub frames: usize,
+    /// The number of frequency bins below the Nyquist frequency, inclusive.
+    pub bins: usize,
+    /// The width of each frequency bin, in Hz.
+    pub bin_width: f32,
+    /// The magnitude-weighted mean frequency averaged over all frames, in Hz.
+    pub centroid: f32,
+    /// The frequency of the strongest bin of the average spectrum, in Hz.
+    pub peak_frequency: f32,
+    /// The ratio of the geometric to the arithmetic mean of the magnitudes averaged over all
+    /// frames, from 0 (tonal) to 1 (noisy).
+    pub flatness: f32,
+    /// The number of frames in which the phase vocoder would reset the phases of most of the
+    /// spectrum for the given transient cutoff.
+    pub transient_frames: usize,
+}
+
+/// Summarize the short-time spectrum of `signal`.
+///
+/// A frame counts as transient when the bins whose magnitude rises past `transient_cutoff`
+/// (measured as in the phase vocoder) hold more than half of its magnitude.
+pub fn spectral_summary(
+    signal: &TimeDomainSignal<f32>,
+    sample_rate: u32,
+    frames: FrameConfig,
+    window_fn: WindowFn,
+    transient_cutoff: f32,
+) -> Result<SpectralSummary, StretchError> {
+    let mags = magnitudes(signal, frames, window_fn)?;
+
+    let bins = mags.ncols();
+    let bin_width = sample_rate as f32 / frames.window_size() as f32;
+
+    let mut centroid = 0.0;
+    let mut flatness = 0.0;
+    let mut transient_frames = 0;
+
+    for (t, frame) in mags.outer_iter().enumerate() {
+        let total = frame.sum();
+        if total <= 0.0 {
+            continue;
+        }
+
+        centroid += frame.iter().enumerate().map(|(k, &m)| k as f32 * bin_width * m).sum::<f32>() / total;
+
+        let log_mean = frame.iter().map(|&m| m.max(f32::MIN_POSITIVE).ln()).sum::<f32>() / bins as f32;
+        flatness += log_mean.exp() / (total / bins as f32);
+
+        if t > 0 {
+            let prev = mags.index_axis(Axis(0), t - 1);
+            let transient: f32 = frame.iter().zip(prev.iter())
+                .filter(|(&m0, &m1)| (m0 - m1) / (m0 + m1) >= transient_cutoff)
+                .map(|(&m0, _)| m0)
+                .sum();
+
+            if transient > 0.5 * total {
+                transient_frames += 1;
+            }
+        }
+    }
+
+    let average = average_spectrum(&mags);
+    let peak_bin = average.iter().enumerate()
+        .fold((0, f32::MIN), |(i, max), (k, &m)| if m > max { (k, m) } else { (i, max) })
+        .0;
+
+    let frame_count = mags.nrows();
+    Ok(SpectralSummary {
+        frames: frame_count,
+        bins,
+        bin_width,
+        centroid: centroid / frame_count as f32,
+        peak_frequency: peak_bin as f32 * bin_width,
+        flatness: flatness / frame_count as f32,
+        transient_frames,
+    })
+}
+
+/// Objective measures of how closely a processed signal renders the original.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityMetrics {
+    /// The difference between the length the algorithm produced, before its output was fitted,
+    /// and the expected length, relative to the expected length.
+    pub length_error: f32,
+    /// The peak level of the processed signal, in dBFS.
+    pub peak_db: f32,
+    /// The RMS difference in dB between the average spectrum of the processed signal and that of
+    /// the original with its frequencies scaled by the pitch ratio, each relative to its maximum
+    /// and floored at -80 dB.
+    pub spectral_distance: f32,
+    /// The difference between the spectral centroid of the processed signal and that of the
+    /// original scaled by the pitch ratio, relative to the latter.
+    pub centroid_error: f32,
+}
+
+/// Measure how closely `processed` renders `original` stretched and shifted by `config`, where
+/// `produced_len` is the length the algorithm produced before its output was fitted, as returned
+/// by [`process_measured`](crate::TimeStretcher::process_measured).
+///
+/// Since the signals are not aligned in time, their spectra are compared averaged over all
+/// frames.
+pub fn quality_metrics(
+    original: &TimeDomainSignal<f32>,
+    processed: &TimeDomainSignal<f32>,
+    produced_len: usize,
+    config: &StretchConfig,
+    frames: FrameConfig,
+    window_fn: WindowFn,
+) -> Result<QualityMetrics, StretchError> {
+    let expected_len = (original.len() as f32 * config.stretch).round().max(1.0);
+    let length_error = (produced_len as f32 - expected_len) / expected_len;
+    let peak = processed.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+
+    let original = average_spectrum(&magnitudes(original, frames, window_fn)?);
+    let processed = average_spectrum(&magnitudes(processed, frames, window_fn)?);
+    let bins = original.len();
+
+    let expected = Array1::from_shape_fn(bins, |k| {
+        let position = k as f32 / config.pitch;
+        let i = position.floor() as usize;
+        let frac = position - i as f32;
+
+        match (original.get(i), original.get(i + 1)) {
+            (Some(&a), Some(&b)) => a + (b - a) * frac,
+            (Some(&a), None) => a * (1.0 - frac),
+            _ => 0.0,
+        }
+    });
+
+    let expected_db = relative_db(&expected);
+    let processed_db = relative_db(&processed);
+    let spectral_distance = (expected_db.iter().zip(processed_db.iter())
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>() / bins as f32)
+        .sqrt();
+
+    let expected_centroid = centroid_bin(&expected);
+
+    Ok(QualityMetrics {
+        length_error,
+        peak_db: 20.0 * peak.max(f32::MIN_POSITIVE).log10(),
+        spectral_distance,
+        centroid_error: (centroid_bin(&processed) - expected_centroid) / expected_centroid.max(f32::MIN_POSITIVE),
+    })
+}
+
+/// Compute the STFT magnitudes of `signal` below the Nyquist frequency, with one row per frame.
+fn magnitudes(signal: &TimeDomainSignal<f32>, frames: FrameConfig, window_fn: WindowFn) -> Result<Array2<f32>, StretchError> {
+    check_input(signal)?;
+
+    let window_size = frames.window_size();
+    let window = build_window(window_fn, window_size);
+    let spectrum = stft(signal, window_size, frames.hop_length(), &window, &Monitor::default())?;
+
+    Ok(spectrum.slice(s![.., ..window_size / 2 + 1]).mapv(|c| c.abs()))
+}
+
+fn average_spectrum(mags: &Array2<f32>) -> Array1<f32> {
+    mags.mean_axis(Axis(0)).unwrap_or_else(|| Array1::zeros(mags.ncols()))
+}
+
+/// Convert magnitudes to dB relative to their maximum, floored at -80 dB.
+fn relative_db(mags: &Array1<f32>) -> Array1<f32> {
+    let max = mags.fold(f32::MIN_POSITIVE, |max, &m| max.max(m));
+    mags.mapv(|m| (20.0 * (m / max).log10()).max(-80.0))
+}
+
+fn centroid_bin(mags: &Array1<f32>) -> f32 {
+    let total = mags.sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+
+    mags.iter().enumerate().map(|(k, &m)| k as f32 * m).sum::<f32>() / total
+}
+
+/// Level statistics of a single channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+    /// The largest absolute sample value.
+    pub peak: f32,
+    /// The root mean square of the samples.
+    pub rms: f32,
+    /// The mean of the samples.
+    pub dc_offset: f32,
+}
+
+pub fn channel_stats(channel: ArrayView1<f32>) -> ChannelStats {
+    let len = channel.len().max(1) as f64;
+    let (mut peak, mut sum, mut squares) = (0.0f32, 0.0f64, 0.0f64);
+
+    for &s in channel {
+        peak = peak.max(s.abs());
+        sum += s as f64;
+        squares += s as f64 * s as f64;
+    }
+
+    ChannelStats {
+        peak,
+        rms: (squares / len).sqrt() as f32,
+        dc_offset: (sum / len) as f32,
+    }
+}
+
+/// Estimate the tempo of `signal` in beats per minute, between 60 and 200.
+///
+/// The tempo is the period, weighted towards 120 BPM, at which the spectral flux of the signal
+/// correlates best with itself. Returns `None` for signals shorter than eight beats at the
+/// slowest tempo or without onsets.
+pub fn estimate_tempo(signal: &TimeDomainSignal<f32>, sample_rate: u32) -> Option<f32> {
+    const WINDOW_SIZE: usize = 1024;
+    const HOP_LENGTH: usize = 512;
+
+    let frame_rate = sample_rate as f32 / HOP_LENGTH as f32;
+    if (signal.len() as f32) < 8.0 * sample_rate as f32 {
+        return None;
+    }
+
+    let frames = FrameConfig::new(WINDOW_SIZE, HOP_LENGTH).ok()?;
+    let mags = magnitudes(signal, frames, hann_window).ok()?;
+
+    let mut flux: Vec<f32> = mags.outer_iter().zip(mags.outer_iter().skip(1))
+        .map(|(prev, frame)| frame.iter().zip(prev).map(|(&m, &p)| (m - p).max(0.0)).sum())
+        .collect();
+    let mean = flux.iter().sum::<f32>() / flux.len() as f32;
+    flux.iter_mut().for_each(|f| *f -= mean);
+
+    let min_lag = (60.0 * frame_rate / 200.0).floor() as usize;
+    let max_lag = (60.0 * frame_rate / 60.0).ceil() as usize;
+
+    let (lag, score) = (min_lag..=max_lag.min(flux.len() - 1))
+        .map(|lag| {
+            let correlation: f32 = flux.iter().zip(&flux[lag..]).map(|(a, b)| a * b).sum::<f32>() / (flux.len() - lag) as f32;
+            let bpm = 60.0 * frame_rate / lag as f32;
+            let weight = (-0.5 * (bpm / 120.0).log2().powi(2)).exp();
+            (lag, correlation * weight)
+        })
+        .fold((0, 0.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+
+    (score > 0.0).then(|| 60.0 * frame_rate / lag as f32)
+}
+
+/// Estimate the dominant fundamental frequency of `signal` in Hz, between 50 Hz and 2 kHz.
+///
+/// Every frame is analysed with the YIN difference function, and the median of the frames
+/// with a clear period is returned. Returns `None` if fewer than a tenth of the frames have one.
+pub fn dominant_pitch(signal: &TimeDomainSignal<f32>, sample_rate: u32) -> Option<f32> {
+    const THRESHOLD: f32 = 0.15;
+
+    let min_lag = (sample_rate as f32 / 2000.0).floor().max(2.0) as usize;
+    let max_lag = (sample_rate as f32 / 50.0).ceil() as usize;
+    let window_size = max_lag + 1;
+    let fft_len = (3 * window_size).next_power_of_two();
+
+    let mut plan = FftPlan::<f32>::new(fft_len);
+    let mut head = vec![Complex::new(0.0, 0.0); fft_len];
+    let mut frame = vec![Complex::new(0.0, 0.0); fft_len];
+    let mut difference = vec![0.0; max_lag + 1];
+
+    let mut pitches = Vec::new();
+    let mut frame_count = 0;
+
+    for start in (0..signal.len().saturating_sub(2 * window_size)).step_by(window_size) {
+        let samples = signal.slice(s![start..start + 2 * window_size]);
+        frame_count += 1;
+
+        // Correlate the first window with the whole frame through the FFT.
+        head.iter_mut().enumerate().for_each(|(i, c)| *c = Complex::new(if i < window_size { samples[i] } else { 0.0 }, 0.0));
+        frame.iter_mut().enumerate().for_each(|(i, c)| *c = Complex::new(samples.get(i).copied().unwrap_or(0.0), 0.0));
+        plan.forward(&mut head);
+        plan.forward(&mut frame);
+        frame.iter_mut().zip(&head).for_each(|(f, h)| *f *= h.conj());
+        plan.inverse(&mut frame);
+        let correlation = |lag: usize| frame[lag].re / fft_len as f32;
+
+        // d(lag) = E(0) + E(lag) - 2 r(lag), with E(lag) the energy of the window at `lag`.
+        let mut energy: f32 = samples.slice(s![..window_size]).iter().map(|s| s * s).sum();
+        let first = energy;
+        if first <= f32::EPSILON {
+            continue;
+        }
+
+        for (lag, d) in difference.iter_mut().enumerate().skip(1) {
+            energy += samples[lag + window_size - 1].powi(2) - samples[lag - 1].powi(2);
+            *d = (first + energy - 2.0 * correlation(lag)).max(0.0);
+        }
+
+        // Cumulative mean normalization, then the first dip below the threshold.
+        let mut running = 0.0;
+        for (lag, d) in difference.iter_mut().enumerate().skip(1) {
+            running += *d;
+            *d *= lag as f32 / running.max(f32::MIN_POSITIVE);
+        }
+
+        let Some(mut lag) = (min_lag..max_lag).find(|&lag| difference[lag] < THRESHOLD) else {
+            continue;
+        };
+        while lag + 1 < max_lag && difference[lag + 1] < difference[lag] {
+            lag += 1;
+        }
+
+        let (a, b, c) = (difference[lag - 1], difference[lag], difference[lag + 1]);
+        let denominator = a - 2.0 * b + c;
+        let offset = if denominator.abs() > f32::EPSILON { 0.5 * (a - c) / denominator } else { 0.0 };
+
+        pitches.push(sample_rate as f32 / (lag as f32 + offset));
+    }
+
+    if pitches.is_empty() || pitches.len() * 10 < frame_count {
+        return None;
+    }
+
+    pitches.sort_by(f32::total_cmp);
+    Some(pitches[pitches.len() / 2])
+}
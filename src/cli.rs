@@ -0,0 +1,587 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use pitch_shifting::{
+    crossfade::CrossfadeShape,
+    dither::{Dither, NoiseShaping},
+    preset::{AlgorithmParams, CrossfadeParams, Preset, PresetError},
+    raw::RawFormat,
+    sample::Encoding,
+    signal::{FileFormat, SignalReadError, SignalWriteError, WriteOptions},
+    units::{Hop, Length},
+    windows::WindowFunction,
+    StretchError,
+};
+use thiserror::Error;
+
+#[derive(Debug, Parser)]
+#[command(name = "pitch-shifting", version, about = "Time stretching and pitch shifting of WAV files")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Change the duration of a file while keeping its pitch.
+    Stretch(StretchArgs),
+    /// Change the pitch of a file while keeping its duration.
+    Shift(ShiftArgs),
+    /// Summarize the short-time spectrum of a file.
+    Analyze(AnalyzeArgs),
+    /// Print the format, levels, tempo and pitch of a file.
+    Info(InfoArgs),
+    /// Stretch and shift many files with the same settings.
+    Batch(BatchArgs),
+    /// Render a file with several algorithms and compare the results.
+    Compare(CompareArgs),
+    /// Search for the parameters of an algorithm that score best on a file.
+    Sweep(SweepArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct StretchArgs {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// The ratio of the output duration to the input duration [default: the preset's stretch].
+    #[arg(short, long)]
+    pub ratio: Option<f32>,
+    #[command(flatten)]
+    pub input_format: InputArgs,
+    #[command(flatten)]
+    pub output_format: OutputArgs,
+    #[command(flatten)]
+    pub algorithm: AlgorithmArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ShiftArgs {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// The pitch shift in semitones.
+    #[arg(short, long, allow_hyphen_values = true, conflicts_with = "ratio")]
+    pub semitones: Option<f32>,
+    /// The ratio of the output pitch to the input pitch [default: the preset's pitch].
+    #[arg(short, long)]
+    pub ratio: Option<f32>,
+    #[command(flatten)]
+    pub input_format: InputArgs,
+    #[command(flatten)]
+    pub output_format: OutputArgs,
+    #[command(flatten)]
+    pub algorithm: AlgorithmArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    pub input: PathBuf,
+    /// The window function of the STFT.
+    #[arg(long, default_value_t = WindowFunction::Hann)]
+    pub window: WindowFunction,
+    /// The STFT window size, in samples (4096), milliseconds (20ms) or seconds (0.1s).
+    #[arg(long, default_value = "4096")]
+    pub window_size: Length,
+    /// The STFT hop length, as a length or as an overlap percentage (75%).
+    #[arg(long, default_value = "1024")]
+    pub hop_length: Hop,
+    /// The relative magnitude rise at which a bin counts as transient.
+    #[arg(long, default_value_t = 0.25)]
+    pub transient_cutoff: f32,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    pub input: PathBuf,
+    /// Print the information as JSON.
+    #[arg(long)]
+    pub json: bool,
+    /// The parameters whose STFT frame count is reported.
+    #[command(flatten)]
+    pub algorithm: AlgorithmArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// Input files, directories or glob patterns such as `stems/*.wav`.
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+    /// The directory to write the outputs to [default: next to each input].
+    #[arg(short, long)]
+    pub output_dir: Option<PathBuf>,
+    /// The output file name, where `{stem}`, `{ext}`, `{ratio}`, `{pitch}`, `{semitones}` and
+    /// `{algorithm}` are replaced by their values, with an `{ext}` of wav for inputs in formats
+    /// that can only be read, the file format's name for raw inputs and raw for raw outputs.
+    /// Files in directories or matching patterns whose names fit the template are taken for
+    /// earlier outputs and skipped.
+    #[arg(short, long, default_value = "{stem}_x{ratio}.{ext}")]
+    pub template: String,
+    /// Search directories recursively.
+    #[arg(short = 'R', long)]
+    pub recursive: bool,
+    /// The ratio of the output duration to the input duration [default: the preset's stretch].
+    #[arg(short, long)]
+    pub ratio: Option<f32>,
+    /// The pitch shift in semitones.
+    #[arg(short, long, allow_hyphen_values = true, conflicts_with = "pitch")]
+    pub semitones: Option<f32>,
+    /// The ratio of the output pitch to the input pitch [default: the preset's pitch].
+    #[arg(long)]
+    pub pitch: Option<f32>,
+    /// The number of files to process in parallel [default: the number of CPUs].
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+    /// Write a JSON report of every file's outcome to this path.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    #[command(flatten)]
+    pub input_format: InputArgs,
+    #[command(flatten)]
+    pub output_format: OutputArgs,
+    #[command(flatten)]
+    pub algorithm: AlgorithmArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    pub input: PathBuf,
+    /// The directory to write the renders to.
+    #[arg(short, long, default_value = "compare")]
+    pub output_dir: PathBuf,
+    /// The ratio of the output duration to the input duration.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub ratio: f32,
+    /// The pitch shift in semitones.
+    #[arg(short, long, allow_hyphen_values = true, conflicts_with = "pitch")]
+    pub semitones: Option<f32>,
+    /// The ratio of the output pitch to the input pitch.
+    #[arg(long)]
+    pub pitch: Option<f32>,
+    /// The algorithms to render with their default parameters.
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Algorithm::Ola, Algorithm::Sola, Algorithm::PhaseVocoder, Algorithm::Granular],
+    )]
+    pub algorithms: Vec<Algorithm>,
+    /// Built-in presets or preset files to render in addition to the algorithms.
+    #[arg(short, long)]
+    pub preset: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SweepArgs {
+    pub input: PathBuf,
+    /// The directory to write the results, the best render and its preset to.
+    #[arg(short, long, default_value = "sweep")]
+    pub output_dir: PathBuf,
+    /// The ratio of the output duration to the input duration.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub ratio: f32,
+    /// The pitch shift in semitones.
+    #[arg(short, long, allow_hyphen_values = true, conflicts_with = "pitch")]
+    pub semitones: Option<f32>,
+    /// The ratio of the output pitch to the input pitch.
+    #[arg(long)]
+    pub pitch: Option<f32>,
+    /// The window sizes (or grain sizes) to try [default: the preset's].
+    #[arg(long, value_delimiter = ',')]
+    pub window_sizes: Vec<Length>,
+    /// The hop lengths to try [default: the preset's].
+    #[arg(long, value_delimiter = ',')]
+    pub hop_lengths: Vec<Hop>,
+    /// The transient cutoffs to try, as a list (0.1,0.25,0.5) or as a range (0.1..0.5), which
+    /// a grid search divides into --steps values [default: the preset's].
+    #[arg(long)]
+    pub transient_cutoffs: Option<SweepRange>,
+    /// The number of values a grid search takes from each range.
+    #[arg(long, default_value_t = 5)]
+    pub steps: usize,
+    /// Try this many random combinations instead of the full grid.
+    #[arg(long)]
+    pub random: Option<usize>,
+    /// The seed of the random search.
+    #[arg(long, default_value_t = 0)]
+    pub search_seed: u64,
+    /// The metric to minimize.
+    #[arg(long, value_enum, default_value_t = Objective::SpectralDistance)]
+    pub objective: Objective,
+    #[command(flatten)]
+    pub algorithm: AlgorithmArgs,
+}
+
+/// The values of a swept parameter, as a list or as a range.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SweepRange {
+    List(Vec<f32>),
+    Range { min: f32, max: f32 },
+}
+
+/// Parse a comma-separated list (`0.1,0.25,0.5`) or a range (`0.1..0.5`).
+impl std::str::FromStr for SweepRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = |v: &str| v.trim().parse::<f32>().map_err(|_| format!("invalid value `{v}`"));
+
+        match s.split_once("..") {
+            Some((min, max)) => {
+                let (min, max) = (value(min)?, value(max)?);
+                if min > max {
+                    return Err(format!("the range `{s}` is empty"));
+                }
+
+                Ok(SweepRange::Range { min, max })
+            },
+            None => s.split(',').map(value).collect::<Result<_, _>>().map(SweepRange::List),
+        }
+    }
+}
+
+/// A quality metric to minimize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Objective {
+    /// The spectral distance to the original, in dB.
+    SpectralDistance,
+    /// The absolute relative error of the spectral centroid.
+    CentroidError,
+    /// The absolute relative error of the output length.
+    LengthError,
+    /// The processing time.
+    Runtime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    Ola,
+    Sola,
+    #[value(name = "pv", alias = "phase-vocoder")]
+    PhaseVocoder,
+    Granular,
+}
+
+impl Algorithm {
+    /// The built-in preset providing the algorithm's default parameters.
+    pub fn default_preset(self) -> &'static str {
+        match self {
+            Algorithm::Ola => "ola",
+            Algorithm::Sola => "sola",
+            Algorithm::PhaseVocoder => "default",
+            Algorithm::Granular => "granular",
+        }
+    }
+
+    pub fn of(params: &AlgorithmParams) -> Self {
+        match params {
+            AlgorithmParams::Ola(_) => Algorithm::Ola,
+            AlgorithmParams::Sola(_) => Algorithm::Sola,
+            AlgorithmParams::PhaseVocoder(_) => Algorithm::PhaseVocoder,
+            AlgorithmParams::Granular(_) => Algorithm::Granular,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Ola => "ola",
+            Algorithm::Sola => "sola",
+            Algorithm::PhaseVocoder => "pv",
+            Algorithm::Granular => "granular",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CrossfadeArg {
+    Linear,
+    EqualPower,
+    RaisedCosine,
+    Adaptive,
+}
+
+/// How input files are read.
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// Read the input as headerless PCM with this sample encoding, byte order (little or big),
+    /// channel count and sample rate, such as int16,little,2,48000. Directories are searched for
+    /// .raw and .pcm files.
+    #[arg(long, value_name = "ENCODING,ENDIANNESS,CHANNELS,RATE")]
+    pub raw: Option<RawInput>,
+}
+
+/// The layout of a headerless PCM input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawInput {
+    pub format: RawFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// Parse the encoding, byte order, channel count and sample rate separated by commas.
+impl std::str::FromStr for RawInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split(',').map(str::trim).collect();
+        let [encoding, endianness, channels, sample_rate] = fields[..] else {
+            return Err(format!("expected an encoding, a byte order, a channel count and a sample rate such as `int16,little,2,48000`, got `{s}`"));
+        };
+
+        Ok(RawInput {
+            format: RawFormat::new(encoding.parse()?, endianness.parse()?),
+            channels: channels.parse().ok()
+                .filter(|&channels| channels > 0)
+                .ok_or_else(|| format!("invalid channel count `{channels}`"))?,
+            sample_rate: sample_rate.parse().ok()
+                .filter(|&rate| rate > 0)
+                .ok_or_else(|| format!("invalid sample rate `{sample_rate}`"))?,
+        })
+    }
+}
+
+/// How rendered files are stored.
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// The file format: wav, flac or aiff [default: from the output's extension, or wav].
+    #[arg(long = "type", value_name = "TYPE")]
+    pub file_format: Option<FileFormat>,
+    /// The sample encoding: int8, int16, int24, int32, float32 or float64 [default: that of the
+    /// input, or int24 if the file format cannot store it].
+    #[arg(short, long)]
+    pub format: Option<Encoding>,
+    /// Add TPDF dither when rounding to integers, with flat, first-order, lipshitz or f-weighted
+    /// noise shaping.
+    #[arg(long)]
+    pub dither: Option<NoiseShaping>,
+    /// The seed of the dither noise.
+    #[arg(long, default_value_t = 0, requires = "dither")]
+    pub dither_seed: u64,
+    /// Write headerless PCM with this sample encoding and byte order, such as int16,little,
+    /// instead of an audio file.
+    #[arg(long, value_name = "ENCODING,ENDIANNESS", conflicts_with_all = ["file_format", "format"])]
+    pub raw_output: Option<RawFormat>,
+}
+
+impl OutputArgs {
+    pub fn options(&self) -> WriteOptions {
+        WriteOptions {
+            format: self.file_format,
+            encoding: self.format,
+            bits_per_sample: None,
+            dither: self.dither.map(|shaping| Dither { shaping, seed: self.dither_seed }),
+        }
+    }
+}
+
+/// The algorithm and its parameters, starting from a preset and overridden by flags.
+#[derive(Debug, Args)]
+pub struct AlgorithmArgs {
+    /// The algorithm to use [default: the preset's algorithm, or pv].
+    #[arg(short, long, value_enum)]
+    pub algorithm: Option<Algorithm>,
+    /// A built-in preset or a TOML or JSON preset file providing the default parameters.
+    #[arg(short, long)]
+    pub preset: Option<String>,
+    /// The window function.
+    #[arg(long)]
+    pub window: Option<WindowFunction>,
+    /// The window size, in samples (4096), milliseconds (20ms) or seconds (0.1s).
+    #[arg(long)]
+    pub window_size: Option<Length>,
+    /// The hop length, as a length or as an overlap percentage (75%).
+    #[arg(long)]
+    pub hop_length: Option<Hop>,
+    /// The relative magnitude rise at which the phase vocoder resets the phases (pv only).
+    #[arg(long)]
+    pub transient_cutoff: Option<f32>,
+    /// Splice segments with an explicit crossfade (ola and sola only).
+    #[arg(long, value_enum)]
+    pub crossfade: Option<CrossfadeArg>,
+    /// The length of the crossfades, from --crossfade or the preset [default: 10ms].
+    #[arg(long)]
+    pub crossfade_overlap: Option<Length>,
+    /// The correlation above which adaptive crossfades use equal gain [default: 0.5].
+    #[arg(long)]
+    pub crossfade_threshold: Option<f32>,
+    /// The length of each grain (granular only).
+    #[arg(long)]
+    pub grain_size: Option<Length>,
+    /// The average number of overlapping grains (granular only).
+    #[arg(long)]
+    pub density: Option<f32>,
+    /// The maximum random offset of each grain's read position (granular only).
+    #[arg(long)]
+    pub position_jitter: Option<Length>,
+    /// The maximum random detuning of each grain, in cents (granular only).
+    #[arg(long)]
+    pub pitch_jitter: Option<f32>,
+    /// The seed of the grain jitter (granular only).
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl AlgorithmArgs {
+    /// Resolve the preset and apply the flags on top of it.
+    pub fn preset(&self) -> Result<Preset, CliError> {
+        let mut preset = match (&self.preset, self.algorithm) {
+            (Some(name), algorithm) => {
+                let preset = Preset::resolve(name)?;
+
+                if let Some(algorithm) = algorithm.filter(|&a| a != Algorithm::of(&preset.params)) {
+                    return Err(CliError::Usage(format!(
+                        "the preset `{name}` uses the {} algorithm, not {}",
+                        Algorithm::of(&preset.params).name(),
+                        algorithm.name(),
+                    )));
+                }
+
+                preset
+            },
+            (None, algorithm) => {
+                let algorithm = algorithm.unwrap_or(Algorithm::PhaseVocoder);
+                Preset::builtin(algorithm.default_preset()).expect("built-in preset")
+            },
+        };
+
+        let algorithm = Algorithm::of(&preset.params);
+        let unsupported = |flag: &str| CliError::Usage(format!("--{flag} does not apply to the {} algorithm", algorithm.name()));
+
+        match &mut preset.params {
+            AlgorithmParams::Ola(params) | AlgorithmParams::Sola(params) => {
+                self.window.inspect(|&w| params.window = w);
+                self.window_size.inspect(|&w| params.frames.window_size = w);
+                self.hop_length.inspect(|&h| params.frames.hop_length = h);
+
+                // The flags override the shape, overlap and threshold of the preset's crossfade.
+                let shape = match self.crossfade {
+                    Some(CrossfadeArg::Linear) => Some(CrossfadeShape::Linear),
+                    Some(CrossfadeArg::EqualPower) => Some(CrossfadeShape::EqualPower),
+                    Some(CrossfadeArg::RaisedCosine) => Some(CrossfadeShape::RaisedCosine),
+                    Some(CrossfadeArg::Adaptive) => Some(CrossfadeShape::Adaptive { threshold: 0.5 }),
+                    None => params.crossfade.map(|c| c.shape),
+                };
+                let shape = match (shape, self.crossfade_threshold) {
+                    (Some(CrossfadeShape::Adaptive { .. }), Some(threshold)) => Some(CrossfadeShape::Adaptive { threshold }),
+                    (_, Some(_)) => {
+                        return Err(CliError::Usage("--crossfade-threshold only applies to adaptive crossfades".to_string()));
+                    },
+                    (shape, None) => shape,
+                };
+
+                params.crossfade = match shape {
+                    Some(shape) => {
+                        let overlap = self.crossfade_overlap
+                            .or(params.crossfade.map(|c| c.overlap))
+                            .unwrap_or(Length::Ms(10.0));
+
+                        Some(CrossfadeParams { shape, overlap })
+                    },
+                    None if self.crossfade_overlap.is_some() => {
+                        return Err(CliError::Usage("--crossfade-overlap needs a crossfade, from --crossfade or the preset".to_string()));
+                    },
+                    None => None,
+                };
+
+                self.reject_transient(unsupported)?;
+                self.reject_grains(unsupported)?;
+            },
+            AlgorithmParams::PhaseVocoder(params) => {
+                self.window.inspect(|&w| params.window = w);
+                self.window_size.inspect(|&w| params.frames.window_size = w);
+                self.hop_length.inspect(|&h| params.frames.hop_length = h);
+                self.transient_cutoff.inspect(|&c| params.transient_cutoff = c);
+
+                self.reject_crossfade(unsupported)?;
+                self.reject_grains(unsupported)?;
+            },
+            AlgorithmParams::Granular(params) => {
+                self.window.inspect(|&w| params.window = w);
+                self.grain_size.inspect(|&g| params.grain_size = g);
+                self.density.inspect(|&d| params.density = d);
+                self.position_jitter.inspect(|&j| params.position_jitter = j);
+                self.pitch_jitter.inspect(|&j| params.pitch_jitter = j);
+                self.seed.inspect(|&s| params.seed = s);
+
+                if self.window_size.is_some() {
+                    return Err(unsupported("window-size"));
+                }
+                if self.hop_length.is_some() {
+                    return Err(unsupported("hop-length"));
+                }
+                self.reject_transient(unsupported)?;
+                self.reject_crossfade(unsupported)?;
+            },
+        }
+
+        Ok(preset)
+    }
+
+    fn reject_transient(&self, unsupported: impl Fn(&str) -> CliError) -> Result<(), CliError> {
+        match self.transient_cutoff {
+            Some(_) => Err(unsupported("transient-cutoff")),
+            None => Ok(()),
+        }
+    }
+
+    fn reject_crossfade(&self, unsupported: impl Fn(&str) -> CliError) -> Result<(), CliError> {
+        let flags = [
+            ("crossfade", self.crossfade.is_some()),
+            ("crossfade-overlap", self.crossfade_overlap.is_some()),
+            ("crossfade-threshold", self.crossfade_threshold.is_some()),
+        ];
+
+        match flags.into_iter().find(|(_, set)| *set) {
+            Some((flag, _)) => Err(unsupported(flag)),
+            None => Ok(()),
+        }
+    }
+
+    fn reject_grains(&self, unsupported: impl Fn(&str) -> CliError) -> Result<(), CliError> {
+        let flags = [
+            ("grain-size", self.grain_size.is_some()),
+            ("density", self.density.is_some()),
+            ("position-jitter", self.position_jitter.is_some()),
+            ("pitch-jitter", self.pitch_jitter.is_some()),
+            ("seed", self.seed.is_some()),
+        ];
+
+        match flags.into_iter().find(|(_, set)| *set) {
+            Some((flag, _)) => Err(unsupported(flag)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("could not read `{}`: {source}", path.display())]
+    Read { path: PathBuf, source: SignalReadError },
+    #[error("could not write `{}`: {source}", path.display())]
+    Write { path: PathBuf, source: SignalWriteError },
+    #[error("`{}`: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error(transparent)]
+    Stretch(#[from] StretchError),
+    #[error(transparent)]
+    Preset(#[from] PresetError),
+    #[error("could not write to standard output: {0}")]
+    Stdout(#[from] std::io::Error),
+    #[error("{0}")]
+    Usage(String),
+    #[error("{failed} of {total} {noun} failed")]
+    Failed { failed: usize, total: usize, noun: &'static str },
+}
+
+impl CliError {
+    pub fn read(path: &Path) -> impl FnOnce(SignalReadError) -> CliError + '_ {
+        move |source| CliError::Read { path: path.to_path_buf(), source }
+    }
+
+    pub fn write(path: &Path) -> impl FnOnce(SignalWriteError) -> CliError + '_ {
+        move |source| CliError::Write { path: path.to_path_buf(), source }
+    }
+
+    pub fn io(path: &Path) -> impl FnOnce(std::io::Error) -> CliError + '_ {
+        move |source| CliError::Io { path: path.to_path_buf(), source }
+    }
+}
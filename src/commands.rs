@@ -0,0 +1,259 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    time::Instant,
+};
+
+use ndarray::Axis;
+use pitch_shifting::{
+    analysis::{channel_stats, dominant_pitch, estimate_tempo, quality_metrics, spectral_summary, QualityMetrics},
+    preset::{AlgorithmParams, Preset},
+    raw,
+    sample::{Encoding, SampleFormat},
+    signal::{self, FileFormat, Signal},
+    units::FrameSpec,
+    windows, FrameConfig, Monitor, StretchConfig,
+};
+
+use serde::Serialize;
+
+use crate::cli::{Algorithm, AnalyzeArgs, CliError, InfoArgs, InputArgs, OutputArgs, ShiftArgs, StretchArgs};
+
+/// The frames over which the spectra of the original and a render are averaged to measure it.
+const METRIC_WINDOW_SIZE: usize = 4096;
+const METRIC_HOP_LENGTH: usize = 1024;
+
+mod batch;
+mod compare;
+mod sweep;
+
+pub use batch::batch;
+pub use compare::compare;
+pub use sweep::sweep;
+
+pub fn stretch(args: &StretchArgs) -> Result<(), CliError> {
+    let preset = args.algorithm.preset()?;
+
+    let mut config = preset.config;
+    if let Some(ratio) = args.ratio {
+        config.stretch = ratio;
+    }
+
+    render(&args.input, &args.output, &preset, &config, &args.input_format, &args.output_format)
+}
+
+pub fn shift(args: &ShiftArgs) -> Result<(), CliError> {
+    let preset = args.algorithm.preset()?;
+
+    let mut config = preset.config;
+    set_pitch(&mut config, args.semitones, args.ratio);
+
+    render(&args.input, &args.output, &preset, &config, &args.input_format, &args.output_format)
+}
+
+/// Set the pitch ratio of `config` from a shift in `semitones` or a `ratio`, if either is given.
+fn set_pitch(config: &mut StretchConfig, semitones: Option<f32>, ratio: Option<f32>) {
+    if let Some(semitones) = semitones {
+        config.pitch = f32::powf(2.0, semitones / 12.0);
+    } else if let Some(ratio) = ratio {
+        config.pitch = ratio;
+    }
+}
+
+/// Render the mono `signal` with `preset`, returning the render, the processing time in seconds
+/// and its quality metrics.
+fn measure(preset: &Preset, signal: &Signal<f32>, config: &StretchConfig) -> Result<(Signal<f32>, f64, QualityMetrics), CliError> {
+    let stretcher = preset.stretcher(signal.sample_rate())?;
+
+    // The length error is measured on the output as the algorithm produced it, before it is
+    // fitted to the expected length.
+    let start = Instant::now();
+    let (processed, produced_len) = stretcher.process_measured(signal.channel(0).to_owned(), config, &Monitor::default())?;
+    let seconds = start.elapsed().as_secs_f64();
+
+    let frames = FrameConfig::new(METRIC_WINDOW_SIZE, METRIC_HOP_LENGTH)?;
+    let metrics = quality_metrics(
+        &signal.channel(0).to_owned(),
+        &processed,
+        produced_len,
+        config,
+        frames,
+        windows::hann_window,
+    )?;
+
+    let processed = signal.clone().with_samples(processed.insert_axis(Axis(1))).expect("one output channel");
+    Ok((processed, seconds, metrics))
+}
+
+/// Render `input` to `output` with every channel processed independently in parallel, storing
+/// the samples according to `output_format` or, by default, as the input stores them, down to its
+/// bit depth.
+fn render(
+    input: &Path,
+    output: &Path,
+    preset: &Preset,
+    config: &StretchConfig,
+    input_format: &InputArgs,
+    output_format: &OutputArgs,
+) -> Result<(), CliError> {
+    let (signal, encoding, bits_per_sample): (Signal<f32>, _, _) = match input_format.raw {
+        Some(raw) => {
+            let signal = raw::read(input, raw.format, raw.channels, raw.sample_rate).map_err(CliError::read(input))?;
+            (signal, Some(raw.format.encoding), raw.format.encoding.bits_per_sample())
+        },
+        None => {
+            let (signal, info) = signal::read_channels(input).map_err(CliError::read(input))?;
+            (signal, Encoding::holding(info.sample_format, info.bits_per_sample), info.bits_per_sample)
+        },
+    };
+
+    let stretcher = preset.stretcher(signal.sample_rate())?;
+    let processed = stretcher.process_channels(signal, config, true)?;
+
+    let mut options = output_format.options();
+    if let Some(format) = output_format.raw_output {
+        return raw::write(&processed, output, format, options.dither).map_err(CliError::write(output));
+    }
+
+    let format = options.format.or_else(|| FileFormat::from_path(output)).unwrap_or_default();
+    if options.encoding.is_none() {
+        options.encoding = encoding.map(|e| format.closest_encoding(e));
+        options.bits_per_sample = Some(bits_per_sample);
+    }
+    signal::write_with(&processed, output, &options).map_err(CliError::write(output))
+}
+
+pub fn analyze(args: &AnalyzeArgs) -> Result<(), CliError> {
+    let signal: Signal<f32> = signal::read_mono(&args.input).map_err(CliError::read(&args.input))?;
+    let sample_rate = signal.sample_rate();
+
+    let frames = FrameSpec::new(args.window_size, args.hop_length).resolve(sample_rate)?;
+    let summary = spectral_summary(&signal.channel(0).to_owned(), sample_rate, frames, args.window.function(), args.transient_cutoff)?;
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "window size:      {} samples", frames.window_size())?;
+    writeln!(out, "hop length:       {} samples", frames.hop_length())?;
+    writeln!(out, "frames:           {}", summary.frames)?;
+    writeln!(out, "bins:             {} ({:.2} Hz wide)", summary.bins, summary.bin_width)?;
+    writeln!(out, "centroid:         {:.1} Hz", summary.centroid)?;
+    writeln!(out, "peak frequency:   {:.1} Hz", summary.peak_frequency)?;
+    writeln!(out, "flatness:         {:.4}", summary.flatness)?;
+    writeln!(out, "transient frames: {}", summary.transient_frames)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct InfoReport {
+    file_format: &'static str,
+    sample_format: &'static str,
+    bits_per_sample: u16,
+    channels: u16,
+    sample_rate: u32,
+    frames: u32,
+    duration: f64,
+    levels: Vec<ChannelLevels>,
+    tempo: Option<f32>,
+    pitch: Option<f32>,
+    algorithm: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stft: Option<StftFrames>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChannelLevels {
+    peak_db: f32,
+    rms_db: f32,
+    dc_offset: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct StftFrames {
+    window_size: usize,
+    hop_length: usize,
+    frames: usize,
+}
+
+pub fn info(args: &InfoArgs) -> Result<(), CliError> {
+    let preset = args.algorithm.preset()?;
+    let (signal, info): (Signal<f32>, _) = signal::read_channels(&args.input)
+        .map_err(CliError::read(&args.input))?;
+
+    let mono = signal.samples().mean_axis(Axis(1)).unwrap_or_default();
+    let frames = match &preset.params {
+        AlgorithmParams::Ola(params) | AlgorithmParams::Sola(params) => Some(params.frames),
+        AlgorithmParams::PhaseVocoder(params) => Some(params.frames),
+        AlgorithmParams::Granular(_) => None,
+    };
+    let stft = frames
+        .map(|frames| frames.resolve(info.sample_rate))
+        .transpose()?
+        .map(|frames| StftFrames {
+            window_size: frames.window_size(),
+            hop_length: frames.hop_length(),
+            frames: frames.frames(mono.len()),
+        });
+
+    let db = |level: f32| 20.0 * level.max(f32::MIN_POSITIVE).log10();
+    let report = InfoReport {
+        file_format: info.file_format.name(),
+        sample_format: match info.sample_format {
+            SampleFormat::Int => "int",
+            SampleFormat::Float => "float",
+        },
+        bits_per_sample: info.bits_per_sample,
+        channels: info.channels,
+        sample_rate: info.sample_rate,
+        frames: info.frames,
+        duration: info.duration(),
+        levels: signal.samples().columns().into_iter()
+            .map(|channel| {
+                let stats = channel_stats(channel);
+                ChannelLevels { peak_db: db(stats.peak), rms_db: db(stats.rms), dc_offset: stats.dc_offset }
+            })
+            .collect(),
+        tempo: estimate_tempo(&mono, info.sample_rate),
+        pitch: dominant_pitch(&mono, info.sample_rate),
+        algorithm: Algorithm::of(&preset.params).name(),
+        stft,
+    };
+
+    let mut out = io::stdout().lock();
+    if args.json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&report).expect("report serializes"))?;
+        return Ok(());
+    }
+
+    writeln!(out, "format:      {}, {}-bit {}", report.file_format, report.bits_per_sample, report.sample_format)?;
+    writeln!(out, "channels:    {}", report.channels)?;
+    writeln!(out, "sample rate: {} Hz", report.sample_rate)?;
+    writeln!(out, "frames:      {}", report.frames)?;
+    writeln!(out, "duration:    {:.3} s", report.duration)?;
+
+    for (i, levels) in report.levels.iter().enumerate() {
+        writeln!(
+            out,
+            "channel {}:   peak {:.1} dBFS, RMS {:.1} dBFS, DC offset {:+.5}",
+            i + 1, levels.peak_db, levels.rms_db, levels.dc_offset,
+        )?;
+    }
+
+    match report.tempo {
+        Some(tempo) => writeln!(out, "tempo:       {tempo:.1} BPM")?,
+        None => writeln!(out, "tempo:       unknown")?,
+    }
+    match report.pitch {
+        Some(pitch) => writeln!(out, "pitch:       {pitch:.1} Hz")?,
+        None => writeln!(out, "pitch:       unknown")?,
+    }
+    match report.stft {
+        Some(stft) => writeln!(
+            out,
+            "stft frames: {} ({}, window size {}, hop length {})",
+            stft.frames, report.algorithm, stft.window_size, stft.hop_length,
+        )?,
+        None => writeln!(out, "stft frames: none ({})", report.algorithm)?,
+    }
+
+    Ok(())
+}
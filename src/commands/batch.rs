@@ -0,0 +1,323 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use pitch_shifting::signal::FileFormat;
+use serde::Serialize;
+
+use crate::cli::{Algorithm, BatchArgs, CliError};
+
+use super::{render, set_pitch};
+
+/// The extensions of the files searched for in directories when reading headerless PCM.
+const RAW_EXTENSIONS: [&str; 2] = ["raw", "pcm"];
+
+/// The outcome of processing a single input.
+#[derive(Debug, Serialize)]
+struct Outcome {
+    input: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    seconds: f64,
+}
+
+impl Outcome {
+    fn failed(input: impl Into<PathBuf>, error: String) -> Self {
+        Outcome { input: input.into(), output: None, error: Some(error), seconds: 0.0 }
+    }
+
+    fn print(&self) {
+        match (&self.output, &self.error) {
+            (_, Some(error)) => eprintln!("failed {}: {error}", self.input.display()),
+            (Some(output), None) => eprintln!("ok     {} -> {} ({:.2} s)", self.input.display(), output.display(), self.seconds),
+            (None, None) => {},
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    succeeded: usize,
+    failed: usize,
+    seconds: f64,
+    files: Vec<Outcome>,
+}
+
+pub fn batch(args: &BatchArgs) -> Result<(), CliError> {
+    let start = Instant::now();
+    let preset = args.algorithm.preset()?;
+
+    let mut config = preset.config;
+    if let Some(ratio) = args.ratio {
+        config.stretch = ratio;
+    }
+    set_pitch(&mut config, args.semitones, args.pitch);
+
+    let values = [
+        ("ratio", format_number(config.stretch)),
+        ("pitch", format_number(config.pitch)),
+        ("semitones", format_number(12.0 * config.pitch.log2())),
+        ("algorithm", Algorithm::of(&preset.params).name().to_string()),
+    ];
+    let raw_input = args.input_format.raw.is_some();
+    let raw_output = args.output_format.raw_output.is_some();
+    let output_name = |input: &Path| {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        // Formats that can only be read are rendered to WAV, and the extension of raw inputs
+        // names no file format.
+        let ext = match (raw_input, raw_output, FileFormat::from_path(input)) {
+            (true, true, _) => input.extension().unwrap_or_default().to_string_lossy(),
+            (true, false, _) => args.output_format.file_format.unwrap_or_default().name().into(),
+            (false, true, _) => "raw".into(),
+            (false, false, Some(format)) if !format.is_writable() => FileFormat::Wav.name().into(),
+            (false, false, _) => input.extension().unwrap_or_default().to_string_lossy(),
+        };
+
+        render_template(&args.template, |key| match key {
+            "stem" => Some(stem.to_string()),
+            "ext" => Some(ext.to_string()),
+            _ => values.iter().find(|(k, _)| *k == key).map(|(_, v)| v.clone()),
+        })
+    };
+    output_name(Path::new("")).map_err(CliError::Usage)?;
+
+    // Files named like the outputs of these settings, with any stem and extension, in a directory
+    // the outputs are written to are outputs of an earlier run rather than inputs.
+    let output_pattern = render_template(&glob::Pattern::escape(&args.template), |key| match key {
+        "stem" | "ext" => Some("*".to_string()),
+        _ => values.iter().find(|(k, _)| *k == key).map(|(_, v)| glob::Pattern::escape(v)),
+    });
+    let output_pattern = output_pattern.ok()
+        .and_then(|pattern| glob::Pattern::new(Path::new(&pattern).file_name()?.to_str()?).ok());
+    let output_dir = args.output_dir.as_deref().map(resolve);
+    let skipped = Cell::new(0);
+    let is_output = |file: &Path| {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let is_output = output_pattern.as_ref().is_some_and(|pattern| pattern.matches(&name))
+            && output_dir.as_ref().is_none_or(|dir| file.parent().is_some_and(|parent| resolve(parent) == *dir));
+
+        skipped.set(skipped.get() + is_output as usize);
+        is_output
+    };
+
+    let mut outcomes = Vec::new();
+    let mut jobs = Vec::new();
+    let mut outputs = HashMap::new();
+
+    for input in expand_inputs(&args.inputs, args.recursive, raw_input, is_output, &mut outcomes) {
+        let name = output_name(&input).map_err(CliError::Usage)?;
+        let output = match &args.output_dir {
+            Some(dir) => dir.join(name),
+            None => input.with_file_name(name),
+        };
+
+        if resolve(&output) == resolve(&input) {
+            outcomes.push(Outcome::failed(input, "the output would overwrite the input".to_string()));
+        } else if let Some(other) = outputs.insert(resolve(&output), input.clone()) {
+            let error = format!("`{}` is also the output of `{}`", output.display(), other.display());
+            outcomes.push(Outcome::failed(input, error));
+        } else {
+            jobs.push((input, output));
+        }
+    }
+
+    match skipped.get() {
+        0 => {},
+        1 => eprintln!("skipped 1 file named like the outputs of these settings"),
+        skipped => eprintln!("skipped {skipped} files named like the outputs of these settings"),
+    }
+    outcomes.iter().for_each(Outcome::print);
+
+    let job_count = args.jobs
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, jobs.len().max(1));
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(jobs.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..job_count {
+            scope.spawn(|| {
+                while let Some((input, output)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let file_start = Instant::now();
+                    let result = output.parent()
+                        .filter(|dir| !dir.as_os_str().is_empty())
+                        .map_or(Ok(()), |dir| std::fs::create_dir_all(dir).map_err(CliError::io(dir)))
+                        .and_then(|()| render(input, output, &preset, &config, &args.input_format, &args.output_format));
+
+                    let outcome = Outcome {
+                        input: input.clone(),
+                        output: result.is_ok().then(|| output.clone()),
+                        error: result.err().map(|e| e.to_string()),
+                        seconds: file_start.elapsed().as_secs_f64(),
+                    };
+                    outcome.print();
+
+                    results.lock().unwrap().push(outcome);
+                }
+            });
+        }
+    });
+
+    outcomes.extend(results.into_inner().unwrap());
+
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    let total = outcomes.len();
+    let seconds = start.elapsed().as_secs_f64();
+
+    eprintln!("{} succeeded, {failed} failed in {seconds:.2} s", total - failed);
+
+    if let Some(path) = &args.report {
+        let report = Report { succeeded: total - failed, failed, seconds, files: outcomes };
+        let json = serde_json::to_string_pretty(&report).expect("report serializes");
+        std::fs::write(path, json).map_err(CliError::io(path))?;
+    }
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(CliError::Failed { failed, total, noun: "files" }),
+    }
+}
+
+/// Expand files, directories and glob patterns into a list of distinct files, recording patterns
+/// that could not be expanded in `outcomes`.
+///
+/// Directories are searched for headerless PCM files if `raw` is set, or audio files otherwise.
+/// Files found in directories or by patterns are skipped if `is_output` recognizes them as
+/// outputs, while files given by name are always kept.
+fn expand_inputs(
+    inputs: &[String],
+    recursive: bool,
+    raw: bool,
+    is_output: impl Fn(&Path) -> bool,
+    outcomes: &mut Vec<Outcome>,
+) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        let path = Path::new(input);
+
+        if path.is_file() {
+            files.push(path.to_path_buf());
+        } else if path.is_dir() {
+            let before = files.len();
+            if let Err(e) = walk(path, recursive, raw, &mut files) {
+                outcomes.push(Outcome::failed(path, e.to_string()));
+            }
+
+            retain_inputs(&mut files, before, &is_output);
+        } else {
+            match glob::glob(input) {
+                Ok(paths) => {
+                    let before = files.len();
+
+                    for entry in paths {
+                        match entry {
+                            Ok(path) if path.is_file() => files.push(path),
+                            Ok(_) => {},
+                            Err(e) => outcomes.push(Outcome::failed(e.path(), e.error().to_string())),
+                        }
+                    }
+
+                    if files.len() == before {
+                        outcomes.push(Outcome::failed(path, "no files match".to_string()));
+                    }
+
+                    retain_inputs(&mut files, before, &is_output);
+                },
+                Err(e) => outcomes.push(Outcome::failed(path, format!("invalid pattern: {e}"))),
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    files
+}
+
+/// Drop the files from `start` on that are outputs.
+fn retain_inputs(files: &mut Vec<PathBuf>, start: usize, is_output: impl Fn(&Path) -> bool) {
+    let found = files.split_off(start);
+    files.extend(found.into_iter().filter(|file| !is_output(file)));
+}
+
+/// The absolute path of `path` with links resolved, as far as it exists, so that different
+/// spellings of the same file compare equal.
+fn resolve(path: &Path) -> PathBuf {
+    // The parent of a bare file name is empty.
+    let path = if path.as_os_str().is_empty() { Path::new(".") } else { path };
+    if let Ok(resolved) = path.canonicalize() {
+        return resolved;
+    }
+
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => resolve(dir).join(name),
+        (_, Some(name)) => resolve(Path::new(".")).join(name),
+        _ => std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+/// Collect the audio files in `dir`, or the headerless PCM files if `raw` is set, recognized by
+/// their extension, in name order.
+fn walk(dir: &Path, recursive: bool, raw: bool, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                walk(&path, recursive, raw, files)?;
+            }
+        } else if raw {
+            let ext = path.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+            if RAW_EXTENSIONS.contains(&ext.as_str()) {
+                files.push(path);
+            }
+        } else if FileFormat::from_path(&path).is_some() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace every `{key}` in `template` by its value.
+fn render_template(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..].find('}')
+            .ok_or_else(|| format!("unclosed `{{` in the template `{template}`"))?;
+        let key = &rest[open + 1..open + close];
+
+        rendered.push_str(&rest[..open]);
+        rendered.push_str(&value(key).ok_or_else(|| format!("unknown placeholder `{{{key}}}` in the template `{template}`"))?);
+        rest = &rest[open + close + 1..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Format a value with at most two decimals, without trailing zeros.
+fn format_number(value: f32) -> String {
+    let formatted = format!("{value:.2}");
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    match trimmed {
+        "-0" => "0".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use pitch_shifting::{
+    analysis::QualityMetrics,
+    preset::Preset,
+    signal::{self, Signal},
+    StretchConfig,
+};
+
+use crate::cli::{CliError, CompareArgs};
+
+use super::{measure, set_pitch};
+
+/// A render of the input with one set of parameters.
+struct Candidate {
+    label: String,
+    preset: Preset,
+}
+
+pub fn compare(args: &CompareArgs) -> Result<(), CliError> {
+    let mut config = StretchConfig { stretch: args.ratio, ..StretchConfig::default() };
+    set_pitch(&mut config, args.semitones, args.pitch);
+    config.validate()?;
+
+    let mut candidates = Vec::new();
+    for algorithm in &args.algorithms {
+        let preset = Preset::builtin(algorithm.default_preset()).expect("built-in preset");
+        candidates.push(Candidate { label: algorithm.name().to_string(), preset });
+    }
+    for name in &args.preset {
+        let label = match Preset::BUILTIN.contains(&name.as_str()) {
+            true => name.clone(),
+            false => Path::new(name).file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        };
+        candidates.push(Candidate { label, preset: Preset::resolve(name)? });
+    }
+
+    for i in 0..candidates.len() {
+        let repeats = candidates[..i].iter().filter(|c| c.label == candidates[i].label).count();
+        if repeats > 0 {
+            candidates[i].label = format!("{}-{}", candidates[i].label, repeats + 1);
+        }
+    }
+
+    let signal: Signal<f32> = signal::read_mono(&args.input).map_err(CliError::read(&args.input))?;
+
+    std::fs::create_dir_all(&args.output_dir).map_err(CliError::io(&args.output_dir))?;
+    let stem = args.input.file_stem().unwrap_or_default().to_string_lossy();
+
+    let mut out = io::stdout().lock();
+    let width = candidates.iter().map(|c| c.label.len()).max().unwrap_or(0).max("name".len());
+    writeln!(
+        out,
+        "{:width$}  {:>10}  {:>12}  {:>9}  {:>13}  {:>14}",
+        "name", "runtime", "length error", "peak", "spectral dist", "centroid error",
+    )?;
+
+    let mut failed = 0;
+    for candidate in &candidates {
+        let output = args.output_dir.join(format!("{stem}_{}.wav", candidate.label));
+
+        match run(candidate, &signal, &config, &output) {
+            Ok((seconds, metrics)) => writeln!(
+                out,
+                "{:width$}  {:>7.1} ms  {:>+11.2}%  {:>6.1} dB  {:>10.2} dB  {:>+13.1}%",
+                candidate.label,
+                seconds * 1000.0,
+                metrics.length_error * 100.0,
+                metrics.peak_db,
+                metrics.spectral_distance,
+                metrics.centroid_error * 100.0,
+            )?,
+            Err(e) => {
+                failed += 1;
+                writeln!(out, "{:width$}  failed: {e}", candidate.label)?;
+            },
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(CliError::Failed { failed, total: candidates.len(), noun: "renders" }),
+    }
+}
+
+/// Render `signal` with the candidate's parameters, write it to `output` and measure it.
+fn run(
+    candidate: &Candidate,
+    signal: &Signal<f32>,
+    config: &StretchConfig,
+    output: &Path,
+) -> Result<(f64, QualityMetrics), CliError> {
+    let (processed, seconds, metrics) = measure(&candidate.preset, signal, config)?;
+    signal::write(&processed, output).map_err(CliError::write(output))?;
+
+    Ok((seconds, metrics))
+}
@@ -0,0 +1,220 @@
+use std::{
+    fmt::Write,
+    io::{self, Write as _},
+};
+
+use pitch_shifting::{
+    analysis::QualityMetrics,
+    preset::{AlgorithmParams, Preset},
+    rng::Rng,
+    signal::{self, Signal},
+    units::{Hop, Length},
+    StretchConfig,
+};
+
+use crate::cli::{Algorithm, CliError, Objective, SweepArgs, SweepRange};
+
+use super::{measure, set_pitch};
+
+/// One combination of swept values, where `None` keeps the preset's value.
+#[derive(Debug, Clone, Copy)]
+struct Trial {
+    window_size: Option<Length>,
+    hop_length: Option<Hop>,
+    transient_cutoff: Option<f32>,
+}
+
+impl Trial {
+    fn apply(&self, params: &mut AlgorithmParams) {
+        match params {
+            AlgorithmParams::Ola(params) | AlgorithmParams::Sola(params) => {
+                self.window_size.inspect(|&w| params.frames.window_size = w);
+                self.hop_length.inspect(|&h| params.frames.hop_length = h);
+            },
+            AlgorithmParams::PhaseVocoder(params) => {
+                self.window_size.inspect(|&w| params.frames.window_size = w);
+                self.hop_length.inspect(|&h| params.frames.hop_length = h);
+                self.transient_cutoff.inspect(|&c| params.transient_cutoff = c);
+            },
+            AlgorithmParams::Granular(params) => {
+                self.window_size.inspect(|&w| params.grain_size = w);
+            },
+        }
+    }
+}
+
+pub fn sweep(args: &SweepArgs) -> Result<(), CliError> {
+    let base = args.algorithm.preset()?;
+
+    let mut config = StretchConfig { stretch: args.ratio, ..StretchConfig::default() };
+    set_pitch(&mut config, args.semitones, args.pitch);
+    config.validate()?;
+
+    let algorithm = Algorithm::of(&base.params);
+    let unsupported = |flag: &str| CliError::Usage(format!("--{flag} cannot be swept for the {} algorithm", algorithm.name()));
+    match algorithm {
+        Algorithm::Granular if !args.hop_lengths.is_empty() => return Err(unsupported("hop-lengths")),
+        Algorithm::PhaseVocoder => {},
+        _ if args.transient_cutoffs.is_some() => return Err(unsupported("transient-cutoffs")),
+        _ => {},
+    }
+
+    let trials = trials(args);
+    if trials.is_empty() {
+        return Err(CliError::Usage("the sweep has no values to try".to_string()));
+    }
+
+    let signal: Signal<f32> = signal::read_mono(&args.input).map_err(CliError::read(&args.input))?;
+    std::fs::create_dir_all(&args.output_dir).map_err(CliError::io(&args.output_dir))?;
+
+    let mut csv = String::from(
+        "window_size,hop_length,transient_cutoff,runtime_ms,length_error,peak_db,spectral_distance,centroid_error,score,error\n",
+    );
+    let mut best: Option<(f64, Preset, Signal<f32>)> = None;
+    let mut failed = 0;
+
+    for (i, trial) in trials.iter().enumerate() {
+        let mut preset = base.clone();
+        preset.config = config;
+        trial.apply(&mut preset.params);
+
+        let values = describe(&preset.params);
+        let label = format!("[{}/{}] {}", i + 1, trials.len(), label(&values));
+        for value in &values {
+            write!(csv, "{},", csv_field(value.as_deref().unwrap_or_default())).unwrap();
+        }
+
+        match measure(&preset, &signal, &config) {
+            Ok((processed, seconds, metrics)) => {
+                let score = score(args.objective, seconds, &metrics);
+                eprintln!("{label}: {score:.4}");
+
+                writeln!(
+                    csv,
+                    "{:.3},{},{},{},{},{score},",
+                    seconds * 1000.0,
+                    metrics.length_error,
+                    metrics.peak_db,
+                    metrics.spectral_distance,
+                    metrics.centroid_error,
+                ).unwrap();
+
+                if best.as_ref().is_none_or(|(best, ..)| score < *best) {
+                    best = Some((score, preset, processed));
+                }
+            },
+            Err(e) => {
+                failed += 1;
+                eprintln!("{label}: failed: {e}");
+                writeln!(csv, ",,,,,,{}", csv_field(&e.to_string())).unwrap();
+            },
+        }
+    }
+
+    let csv_path = args.output_dir.join("sweep.csv");
+    std::fs::write(&csv_path, csv).map_err(CliError::io(&csv_path))?;
+
+    let Some((score, preset, processed)) = best else {
+        return Err(CliError::Failed { failed, total: trials.len(), noun: "trials" });
+    };
+
+    let stem = args.input.file_stem().unwrap_or_default().to_string_lossy();
+    let render_path = args.output_dir.join(format!("{stem}_best.wav"));
+    signal::write(&processed, &render_path).map_err(CliError::write(&render_path))?;
+
+    let preset_path = args.output_dir.join("best.toml");
+    preset.save(&preset_path)?;
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "best: {} (score {score:.4})", label(&describe(&preset.params)))?;
+    writeln!(out, "wrote {}, {} and {}", csv_path.display(), render_path.display(), preset_path.display())?;
+
+    Ok(())
+}
+
+/// List the combinations to try: the full grid, or random draws from it with ranges sampled
+/// continuously.
+fn trials(args: &SweepArgs) -> Vec<Trial> {
+    let window_sizes = or_default(args.window_sizes.iter().copied().map(Some).collect());
+    let hop_lengths = or_default(args.hop_lengths.iter().copied().map(Some).collect());
+    let transient_cutoffs = match &args.transient_cutoffs {
+        None => vec![None],
+        Some(SweepRange::List(values)) => values.iter().copied().map(Some).collect(),
+        Some(SweepRange::Range { min, max }) => (0..args.steps)
+            .map(|i| Some(min + (max - min) * i as f32 / (args.steps - 1).max(1) as f32))
+            .collect(),
+    };
+
+    let Some(count) = args.random else {
+        return window_sizes.iter()
+            .flat_map(|&window_size| hop_lengths.iter().map(move |&hop_length| (window_size, hop_length)))
+            .flat_map(|(window_size, hop_length)| {
+                transient_cutoffs.iter().map(move |&transient_cutoff| Trial { window_size, hop_length, transient_cutoff })
+            })
+            .collect();
+    };
+
+    let mut rng = Rng::new(args.search_seed);
+
+    (0..count)
+        .map(|_| {
+            let window_size = *choose(&mut rng, &window_sizes);
+            let hop_length = *choose(&mut rng, &hop_lengths);
+            let transient_cutoff = match &args.transient_cutoffs {
+                Some(SweepRange::Range { min, max }) => Some(min + (max - min) * rng.next_f32()),
+                _ => *choose(&mut rng, &transient_cutoffs),
+            };
+
+            Trial { window_size, hop_length, transient_cutoff }
+        })
+        .collect()
+}
+
+fn choose<'a, T>(rng: &mut Rng, values: &'a [T]) -> &'a T {
+    &values[(rng.next_u64() % values.len() as u64) as usize]
+}
+
+fn or_default<T>(values: Vec<Option<T>>) -> Vec<Option<T>> {
+    if values.is_empty() { vec![None] } else { values }
+}
+
+/// Format the swept parameters of `params`, with `None` for those the algorithm does not have.
+fn describe(params: &AlgorithmParams) -> [Option<String>; 3] {
+    match params {
+        AlgorithmParams::Ola(params) | AlgorithmParams::Sola(params) => {
+            [Some(params.frames.window_size.to_string()), Some(params.frames.hop_length.to_string()), None]
+        },
+        AlgorithmParams::PhaseVocoder(params) => [
+            Some(params.frames.window_size.to_string()),
+            Some(params.frames.hop_length.to_string()),
+            Some(params.transient_cutoff.to_string()),
+        ],
+        AlgorithmParams::Granular(params) => [Some(params.grain_size.to_string()), None, None],
+    }
+}
+
+fn label(values: &[Option<String>; 3]) -> String {
+    ["window", "hop", "cutoff"].iter().zip(values)
+        .filter_map(|(name, value)| value.as_ref().map(|value| format!("{name} {value}")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Score a trial, where lower is better.
+fn score(objective: Objective, seconds: f64, metrics: &QualityMetrics) -> f64 {
+    match objective {
+        Objective::SpectralDistance => metrics.spectral_distance as f64,
+        Objective::CentroidError => metrics.centroid_error.abs() as f64,
+        Objective::LengthError => metrics.length_error.abs() as f64,
+        Objective::Runtime => seconds,
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,164 @@
+use symphonia::{
+    core::{
+        audio::{SampleBuffer, SignalSpec},
+        codecs::{self, CodecType, Decoder, DecoderOptions},
+        errors::Error,
+        formats::{FormatOptions, FormatReader},
+        io::{MediaSource, MediaSourceStream},
+        meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+        probe::Hint,
+    },
+    default::{get_codecs, get_probe},
+};
+
+use crate::{
+    sample::SampleFormat,
+    signal::{FileFormat, Metadata, SignalInfo, SignalReadError},
+};
+
+/// The channels of the WAVE_FORMAT_EXTENSIBLE channel mask, which symphonia's channel bits
+/// start with.
+const WAVE_CHANNELS: u32 = 0x3_FFFF;
+
+/// The audio track of a file in a lossy format, with the decoder of its codec.
+pub(crate) struct CompressedStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    file_format: FileFormat,
+    /// The rate and channels of the decoded audio, known from the codec parameters or once the
+    /// first packet is decoded.
+    spec: Option<SignalSpec>,
+    frames: u64,
+    pub(crate) metadata: Metadata,
+}
+
+impl CompressedStream {
+    /// Probe `source` for a container holding an audio track, starting with `file_format`, and
+    /// set up the decoder of its codec.
+    pub(crate) fn open(source: Box<dyn MediaSource>, file_format: FileFormat) -> Result<Self, SignalReadError> {
+        let mut hint = Hint::new();
+        hint.with_extension(file_format.extensions()[0]);
+
+        // Gapless playback trims the encoder delay and padding of MP3 and Vorbis.
+        let mut probed = get_probe().format(
+            &hint,
+            MediaSourceStream::new(source, Default::default()),
+            &FormatOptions { enable_gapless: true, ..Default::default() },
+            &MetadataOptions::default(),
+        )?;
+
+        let track = probed.format.tracks().iter()
+            .find(|track| track.codec_params.codec != codecs::CODEC_TYPE_NULL)
+            .ok_or(SignalReadError::NoAudioTrack)?;
+        let params = &track.codec_params;
+
+        if get_codecs().get_codec(params.codec).is_none() {
+            return Err(SignalReadError::UnsupportedCodec(codec_name(params.codec)));
+        }
+        let decoder = get_codecs().make(params, &DecoderOptions::default())?;
+
+        let spec = params.sample_rate.zip(params.channels).map(|(rate, channels)| SignalSpec::new(rate, channels));
+        let frames = params.n_frames.unwrap_or(0);
+        let track_id = track.id;
+
+        // Tags can come both from before the container, such as ID3v2 tags, and from inside it.
+        let mut metadata = Metadata::default();
+        if let Some(revision) = probed.metadata.get().as_ref().and_then(|log| log.current()) {
+            read_tags(&mut metadata, revision);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            read_tags(&mut metadata, revision);
+        }
+
+        Ok(Self { format: probed.format, decoder, track_id, file_format, spec, frames, metadata })
+    }
+
+    pub(crate) fn info(&self) -> SignalInfo {
+        let channels = self.spec.map_or(0, |spec| spec.channels.count());
+        let mask = self.spec.map(|spec| spec.channels.bits() & WAVE_CHANNELS);
+
+        SignalInfo {
+            file_format: self.file_format,
+            channels: channels as u16,
+            sample_rate: self.spec.map_or(0, |spec| spec.rate),
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+            frames: self.frames as u32,
+            // Like WAV files, mono and stereo have no speaker positions unless they are named.
+            channel_mask: mask.filter(|_| channels > 2),
+        }
+    }
+
+    /// Decode the audio track into interleaved samples.
+    ///
+    /// Packets that fail to decode are skipped, as the decoder recovers at the next one.
+    pub(crate) fn read_interleaved(&mut self) -> Result<Vec<f32>, SignalReadError> {
+        let mut samples = Vec::new();
+        let mut buffer: Option<SampleBuffer<f32>> = None;
+
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(Error::DecodeError(_)) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let spec = *decoded.spec();
+            if buffer.as_ref().is_none_or(|b| b.capacity() < decoded.capacity() * spec.channels.count()) {
+                buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+            }
+            let buffer = buffer.as_mut().unwrap();
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buffer.samples());
+
+            self.spec = Some(spec);
+        }
+
+        let channels = self.spec.map_or(1, |spec| spec.channels.count().max(1));
+        self.frames = (samples.len() / channels) as u64;
+        Ok(samples)
+    }
+}
+
+/// Fill the fields of `metadata` that are still empty from the tags of `revision`.
+fn read_tags(metadata: &mut Metadata, revision: &MetadataRevision) {
+    for tag in revision.tags() {
+        let field = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => &mut metadata.title,
+            Some(StandardTagKey::Artist) => &mut metadata.artist,
+            Some(StandardTagKey::Album) => &mut metadata.album,
+            Some(StandardTagKey::Date | StandardTagKey::ReleaseDate) => &mut metadata.date,
+            Some(StandardTagKey::Genre) => &mut metadata.genre,
+            Some(StandardTagKey::Comment | StandardTagKey::Description) => &mut metadata.comment,
+            Some(StandardTagKey::Encoder) => &mut metadata.software,
+            _ => continue,
+        };
+
+        field.get_or_insert_with(|| tag.value.to_string());
+    }
+}
+
+/// The name of a codec that cannot be decoded, for error messages.
+fn codec_name(codec: CodecType) -> String {
+    let name = match codec {
+        codecs::CODEC_TYPE_OPUS => "Opus",
+        codecs::CODEC_TYPE_SPEEX => "Speex",
+        codecs::CODEC_TYPE_FLAC => "FLAC",
+        codecs::CODEC_TYPE_ALAC => "ALAC",
+        codecs::CODEC_TYPE_EAC3 => "E-AC-3",
+        codecs::CODEC_TYPE_WAVPACK => "WavPack",
+        _ => return codec.to_string(),
+    };
+
+    name.to_string()
+}
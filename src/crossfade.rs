@@ -0,0 +1,118 @@
+use ndarray::{s, ArrayView1, ArrayViewMut1};
+use serde::{Deserialize, Serialize};
+
+use crate::sample::{AudioSample, ConvertSample};
+
+/// The gain curves used to blend two segments at a splice point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossfadeShape {
+    /// Straight gain ramps whose sum is always one (equal-gain).
+    Linear,
+    /// Quarter-period sine/cosine ramps whose squares sum to one (equal-power).
+    EqualPower,
+    /// Half-period cosine ramps whose sum is always one, with flat endpoints.
+    RaisedCosine,
+    /// Measure the normalized correlation of the overlapping segments at every splice,
+    /// using an equal-gain (raised cosine) fade when it is at least `threshold` and an
+    /// equal-power fade otherwise.
+    Adaptive { threshold: f32 },
+}
+
+impl CrossfadeShape {
+    /// Compute the `(fade_out, fade_in)` gains at position `x` in `[0, 1]` along the overlap,
+    /// given the `correlation` of the overlapping segments.
+    pub fn gains(self, x: f32, correlation: f32) -> (f32, f32) {
+        match self {
+            CrossfadeShape::Linear => (1.0 - x, x),
+            CrossfadeShape::EqualPower => {
+                let theta = x * std::f32::consts::FRAC_PI_2;
+                (theta.cos(), theta.sin())
+            },
+            CrossfadeShape::RaisedCosine => {
+                let fade_in = 0.5 - 0.5 * f32::cos(std::f32::consts::PI * x);
+                (1.0 - fade_in, fade_in)
+            },
+            CrossfadeShape::Adaptive { threshold } => {
+                if correlation >= threshold {
+                    CrossfadeShape::RaisedCosine.gains(x, correlation)
+                } else {
+                    CrossfadeShape::EqualPower.gains(x, correlation)
+                }
+            },
+        }
+    }
+}
+
+/// A crossfade applied where two synthesized segments overlap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossfade {
+    pub shape: CrossfadeShape,
+    /// The length of the overlap between consecutive segments, in samples.
+    pub overlap: usize,
+}
+
+impl Crossfade {
+    pub fn new(shape: CrossfadeShape, overlap: usize) -> Self {
+        Self { shape, overlap }
+    }
+}
+
+/// Compute the normalized cross-correlation of two equally long segments, in `[-1, 1]`.
+///
+/// Returns `0.0` if either segment is silent.
+pub fn correlation<T>(a: ArrayView1<T>, b: ArrayView1<T>) -> f32
+where
+    T: AudioSample,
+{
+    let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+
+    for (&x, &y) in a.iter().zip(b.iter()) {
+        let x: f32 = x.convert_sample();
+        let y: f32 = y.convert_sample();
+        ab += x * y;
+        aa += x * x;
+        bb += y * y;
+    }
+
+    let norm = f32::sqrt(aa * bb);
+    if norm > 0.0 { ab / norm } else { 0.0 }
+}
+
+/// Write `segment` into `output` at `position`, crossfading its start with the samples already
+/// written before `written`.
+///
+/// Returns the end of the written region, which should be passed as `written` to the next splice.
+pub fn splice<T>(
+    mut output: ArrayViewMut1<T>,
+    segment: ArrayView1<T>,
+    position: usize,
+    written: usize,
+    crossfade: &Crossfade,
+) -> usize
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    let len = segment.len().min(output.len().saturating_sub(position));
+    let overlap = crossfade.overlap
+        .min(written.saturating_sub(position))
+        .min(len);
+
+    let correlation = correlation(output.slice(s![position..position + overlap]), segment.slice(s![..overlap]));
+
+    for j in 0..overlap {
+        let x = (j + 1) as f32 / (overlap + 1) as f32;
+        let (fade_out, fade_in) = crossfade.shape.gains(x, correlation);
+
+        let a: f32 = output[position + j].convert_sample();
+        let b: f32 = segment[j].convert_sample();
+        output[position + j] = (a * fade_out + b * fade_in).convert_sample();
+    }
+
+    output.slice_mut(s![position + overlap..position + len])
+        .assign(&segment.slice(s![overlap..len]));
+
+    // A short final segment can end before the previous one.
+    written.max(position + len)
+}
@@ -0,0 +1,164 @@
+use std::{fmt, str::FromStr};
+
+use crate::rng::Rng;
+
+/// The spectral shape of the requantization noise, set by feeding the error of past samples
+/// back into the next ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseShaping {
+    /// White noise.
+    #[default]
+    Flat,
+    /// Noise rising by 6 dB per octave, out of the low frequencies.
+    FirstOrder,
+    /// Lipshitz's 5-tap filter, moving the noise away from the 2–5 kHz region where hearing is
+    /// most sensitive.
+    Lipshitz,
+    /// Wannamaker's 9-tap filter, following the F-weighted threshold of hearing.
+    FWeighted,
+}
+
+impl NoiseShaping {
+    pub const ALL: [NoiseShaping; 4] = [
+        NoiseShaping::Flat,
+        NoiseShaping::FirstOrder,
+        NoiseShaping::Lipshitz,
+        NoiseShaping::FWeighted,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoiseShaping::Flat => "flat",
+            NoiseShaping::FirstOrder => "first-order",
+            NoiseShaping::Lipshitz => "lipshitz",
+            NoiseShaping::FWeighted => "f-weighted",
+        }
+    }
+
+    /// The error feedback coefficients, for the errors of the previous samples from the most
+    /// recent on. The weighted filters are designed for 44.1 kHz.
+    fn coefficients(self) -> &'static [f64] {
+        match self {
+            NoiseShaping::Flat => &[],
+            NoiseShaping::FirstOrder => &[1.0],
+            NoiseShaping::Lipshitz => &[2.033, -2.165, 1.959, -1.590, 0.6149],
+            NoiseShaping::FWeighted => &[2.412, -3.370, 3.937, -4.174, 3.353, -2.205, 1.281, -0.569, 0.0847],
+        }
+    }
+}
+
+impl fmt::Display for NoiseShaping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for NoiseShaping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NoiseShaping::ALL.into_iter()
+            .find(|n| n.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown noise shaping `{s}`"))
+    }
+}
+
+/// Triangular (TPDF) dither of ±1 LSB added before rounding samples to integers, which turns the
+/// distortion of rounding quiet signals into a constant noise floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dither {
+    pub shaping: NoiseShaping,
+    /// The seed of the dither noise, so that the same input always gives the same output.
+    pub seed: u64,
+}
+
+impl Dither {
+    /// A quantizer to `bits`-bit integers for `channels` interleaved channels.
+    pub fn quantizer(&self, bits: u16, channels: usize) -> Quantizer {
+        let order = self.shaping.coefficients().len();
+
+        Quantizer {
+            rng: Rng::new(self.seed),
+            coefficients: self.shaping.coefficients(),
+            scale: ((1u64 << (bits - 1)) - 1) as f64,
+            errors: vec![vec![0.0; order]; channels.max(1)],
+            channel: 0,
+        }
+    }
+}
+
+/// Rounds interleaved samples to a lower resolution with dither and noise shaping, keeping the
+/// error history of every channel.
+#[derive(Debug, Clone)]
+pub struct Quantizer {
+    rng: Rng,
+    coefficients: &'static [f64],
+    /// Full scale, in steps of the target resolution.
+    scale: f64,
+    errors: Vec<Vec<f64>>,
+    channel: usize,
+}
+
+impl Quantizer {
+    /// Quantize the next sample, in `[-1, 1]`, returning a value that converts to the target
+    /// resolution without further rounding.
+    pub fn quantize(&mut self, sample: f64) -> f64 {
+        let channel = self.channel;
+        self.channel = (channel + 1) % self.errors.len();
+        let errors = &mut self.errors[channel];
+
+        let feedback: f64 = self.coefficients.iter().zip(errors.iter()).map(|(c, e)| c * e).sum();
+        let target = sample * self.scale - feedback;
+
+        let dither = (self.rng.next_f32() - self.rng.next_f32()) as f64;
+        let quantized = (target + dither).round().clamp(-self.scale - 1.0, self.scale);
+
+        // The error of clipped samples is measured against the clipped target, so that it cannot
+        // build up through the feedback.
+        if !errors.is_empty() {
+            errors.rotate_right(1);
+            errors[0] = quantized - target.clamp(-self.scale - 1.0, self.scale);
+        }
+
+        quantized / self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A quiet sine, quantized to 16 bits with `dither`.
+    fn quantize(dither: Dither) -> Vec<f64> {
+        let mut quantizer = dither.quantizer(16, 1);
+        (0..4096)
+            .map(|n| quantizer.quantize(0.1 * (n as f64 * 0.05).sin()))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_output() {
+        for shaping in NoiseShaping::ALL {
+            let dither = Dither { shaping, seed: 7 };
+            assert_eq!(quantize(dither), quantize(dither), "{shaping}");
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_output() {
+        for shaping in NoiseShaping::ALL {
+            assert_ne!(quantize(Dither { shaping, seed: 1 }), quantize(Dither { shaping, seed: 2 }), "{shaping}");
+        }
+    }
+
+    #[test]
+    fn flat_error_stays_within_one_and_a_half_steps() {
+        let scale = i16::MAX as f64;
+        let quantized = quantize(Dither { shaping: NoiseShaping::Flat, seed: 3 });
+
+        for (n, value) in quantized.into_iter().enumerate() {
+            let sample = 0.1 * (n as f64 * 0.05).sin();
+            assert!((value - sample).abs() * scale <= 1.5, "sample {n} is off by {} steps", (value - sample) * scale);
+        }
+    }
+}
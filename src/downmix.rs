@@ -0,0 +1,149 @@
+use ndarray::Array1;
+use thiserror::Error;
+
+use crate::{
+    sample::{AudioSample, ConvertSample},
+    signal::Signal,
+};
+
+/// Speaker positions of the WAVE_FORMAT_EXTENSIBLE channel mask, in the order their channels
+/// appear in a file.
+pub mod speaker {
+    pub const FRONT_LEFT: u32 = 0x1;
+    pub const FRONT_RIGHT: u32 = 0x2;
+    pub const FRONT_CENTER: u32 = 0x4;
+    pub const LOW_FREQUENCY: u32 = 0x8;
+    pub const BACK_LEFT: u32 = 0x10;
+    pub const BACK_RIGHT: u32 = 0x20;
+    pub const FRONT_LEFT_OF_CENTER: u32 = 0x40;
+    pub const FRONT_RIGHT_OF_CENTER: u32 = 0x80;
+    pub const BACK_CENTER: u32 = 0x100;
+    pub const SIDE_LEFT: u32 = 0x200;
+    pub const SIDE_RIGHT: u32 = 0x400;
+
+    /// 5.1 with back surrounds, the layout assumed for six channels without a mask.
+    pub const LAYOUT_5_1: u32 = FRONT_LEFT | FRONT_RIGHT | FRONT_CENTER | LOW_FREQUENCY | BACK_LEFT | BACK_RIGHT;
+    /// 5.1 with side surrounds.
+    pub const LAYOUT_5_1_SIDE: u32 = FRONT_LEFT | FRONT_RIGHT | FRONT_CENTER | LOW_FREQUENCY | SIDE_LEFT | SIDE_RIGHT;
+    /// 7.1 with back and side surrounds, the layout assumed for eight channels without a mask.
+    pub const LAYOUT_7_1: u32 = LAYOUT_5_1 | SIDE_LEFT | SIDE_RIGHT;
+}
+
+/// How the channels of a signal are mixed into one.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Downmix {
+    /// Add the channels without scaling, which can clip.
+    Sum,
+    /// Average the channels.
+    Average,
+    /// Keep the channel with the given index and drop the others.
+    Channel(usize),
+    /// Weight every channel by its gain and add them.
+    Gains(Vec<f32>),
+    /// Use the ITU-R BS.775 coefficients for surround layouts, identified by the channel mask
+    /// or assumed from the channel count (5.1 for six channels, 7.1 for eight), and
+    /// [`Average`](Downmix::Average) the channels of any other signal.
+    #[default]
+    Standard,
+}
+
+impl Downmix {
+    /// The gain of every one of `channels` channels laid out according to `channel_mask`, a
+    /// WAVE_FORMAT_EXTENSIBLE channel mask.
+    pub fn gains(&self, channels: usize, channel_mask: Option<u32>) -> Result<Vec<f32>, DownmixError> {
+        if channels == 0 {
+            return Err(DownmixError::NoChannels);
+        }
+
+        Ok(match self {
+            Downmix::Sum => vec![1.0; channels],
+            Downmix::Average => vec![1.0 / channels as f32; channels],
+            &Downmix::Channel(channel) => {
+                if channel >= channels {
+                    return Err(DownmixError::ChannelOutOfRange { channel, channels });
+                }
+
+                (0..channels).map(|c| if c == channel { 1.0 } else { 0.0 }).collect()
+            },
+            Downmix::Gains(gains) => {
+                if gains.len() != channels {
+                    return Err(DownmixError::GainCount { gains: gains.len(), channels });
+                }
+
+                gains.clone()
+            },
+            Downmix::Standard => {
+                let mask = channel_mask.filter(|mask| mask.count_ones() as usize == channels)
+                    .or(match channels {
+                        6 => Some(speaker::LAYOUT_5_1),
+                        8 => Some(speaker::LAYOUT_7_1),
+                        _ => None,
+                    });
+
+                match mask.filter(|&mask| is_surround(mask)) {
+                    Some(mask) => surround_gains(mask),
+                    None => vec![1.0 / channels as f32; channels],
+                }
+            },
+        })
+    }
+
+    /// Mix the channels of `signal` into one, according to its layout.
+    pub fn apply<T>(&self, signal: &Signal<T>) -> Result<Signal<T>, DownmixError>
+    where
+        T: AudioSample,
+        f32: ConvertSample<T>,
+    {
+        let gains = self.gains(signal.channels(), signal.layout().mask())?;
+
+        let mixed = signal.samples().rows().into_iter()
+            .map(|frame| {
+                let mixed: f32 = frame.iter().zip(&gains).map(|(&s, &g)| g * ConvertSample::<f32>::convert_sample(s)).sum();
+                mixed.convert_sample()
+            })
+            .collect::<Array1<T>>();
+
+        Ok(Signal::mono(mixed, signal.sample_rate()).with_metadata(signal.metadata().clone()))
+    }
+}
+
+/// Whether `mask` is a surround layout such as 5.1 or 7.1, with left, right and center speakers
+/// in front and at least two surround speakers.
+fn is_surround(mask: u32) -> bool {
+    use speaker::*;
+
+    let fronts = FRONT_LEFT | FRONT_RIGHT | FRONT_CENTER;
+    let surrounds = BACK_LEFT | BACK_RIGHT | SIDE_LEFT | SIDE_RIGHT;
+
+    mask & fronts == fronts && (mask & surrounds).count_ones() >= 2
+}
+
+/// The ITU-R BS.775 stereo downmix, `L = FL + 0.707 C + 0.707 Ls` and likewise for the right,
+/// averaged to mono. The low-frequency channel is dropped.
+fn surround_gains(mask: u32) -> Vec<f32> {
+    use speaker::*;
+
+    const MINUS_3_DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    (0..32)
+        .map(|bit| 1 << bit)
+        .filter(|speaker| mask & speaker != 0)
+        .map(|speaker| match speaker {
+            FRONT_LEFT | FRONT_RIGHT => 0.5,
+            FRONT_CENTER | BACK_CENTER => MINUS_3_DB,
+            LOW_FREQUENCY => 0.0,
+            FRONT_LEFT_OF_CENTER | FRONT_RIGHT_OF_CENTER => 0.5,
+            _ => 0.5 * MINUS_3_DB,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DownmixError {
+    #[error("the signal has no channels")]
+    NoChannels,
+    #[error("cannot keep channel {channel} of a signal with {channels} channels")]
+    ChannelOutOfRange { channel: usize, channels: usize },
+    #[error("got {gains} gains for a signal with {channels} channels")]
+    GainCount { gains: usize, channels: usize },
+}
@@ -1,8 +1,15 @@
+use std::sync::Arc;
+
 use ndarray::{s, Array1, Array2, ArrayView1, Axis};
-use rustfft::{FftNum, FftPlanner};
+use rustfft::{Fft, FftNum, FftPlanner};
 use num_complex::Complex;
 
-use crate::{sample::AudioSample, signal::{SpectrumSignal, TimeDomainSignal}};
+use crate::{
+    progress::{Monitor, Stage},
+    sample::AudioSample,
+    signal::{SpectrumSignal, TimeDomainSignal},
+    stretcher::{FrameConfig, StretchError},
+};
 
 /// Compute the Fast Fourier Transform of the given signal in the time domain.
 pub fn fft<T>(signal: ArrayView1<T>) -> Array1<Complex<T>>
@@ -32,16 +39,71 @@ where
     buffer.into_iter().map(|c| c.re).collect()
 }
 
+/// Forward and inverse Fast Fourier Transforms of a fixed size, planned once with preallocated
+/// scratch space so that transforming never allocates.
+pub struct FftPlan<T: FftNum> {
+    forward: Arc<dyn Fft<T>>,
+    inverse: Arc<dyn Fft<T>>,
+    scratch: Vec<Complex<T>>,
+}
+
+impl<T: FftNum> FftPlan<T> {
+    pub fn new(len: usize) -> Self {
+        let mut planner = FftPlanner::<T>::new();
+        let forward = planner.plan_fft_forward(len);
+        let inverse = planner.plan_fft_inverse(len);
+        let scratch_len = forward.get_inplace_scratch_len().max(inverse.get_inplace_scratch_len());
+
+        Self {
+            forward,
+            inverse,
+            scratch: vec![Complex { re: T::zero(), im: T::zero() }; scratch_len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Compute the Fast Fourier Transform of `buffer` in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `buffer` does not match the planned length.
+    pub fn forward(&mut self, buffer: &mut [Complex<T>]) {
+        assert_eq!(buffer.len(), self.len());
+        self.forward.process_with_scratch(buffer, &mut self.scratch);
+    }
+
+    /// Compute the unnormalized inverse Fast Fourier Transform of `buffer` in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `buffer` does not match the planned length.
+    pub fn inverse(&mut self, buffer: &mut [Complex<T>]) {
+        assert_eq!(buffer.len(), self.len());
+        self.inverse.process_with_scratch(buffer, &mut self.scratch);
+    }
+}
+
 /// Compute the short-time fourier transform of the given signal in the time domain.
 pub fn stft<T>(
     signal: &TimeDomainSignal<T>,
     window_size: usize,
     hop_length: usize,
     window: &Array1<T>,
-) -> SpectrumSignal<T>
+    monitor: &Monitor,
+) -> Result<SpectrumSignal<T>, StretchError>
 where
     T: FftNum + AudioSample,
 {
+    FrameConfig::new(window_size, hop_length)?;
+    check_window(window, window_size)?;
+
     let len = signal.len().div_ceil(hop_length);
     let mut spectrum_samples = Array2::from_elem((len, window_size), Complex { re: T::zero(), im: T::zero() });
 
@@ -58,9 +120,11 @@ where
 
         let mut window_spectrum = spectrum_samples.slice_mut(s![index, ..]);
         window_spectrum += &spectrum;
+
+        monitor.frame(Stage::Stft, index + 1, spectrum_samples.nrows())?;
     }
 
-    spectrum_samples
+    Ok(spectrum_samples)
 }
 
 /// Compute the inverse short-time fourier transform of the given signal.
@@ -70,10 +134,21 @@ pub fn istft<T>(
     hop_length: usize,
     num_samples: usize,
     window: &Array1<T>,
-) -> Array1<T>
+    monitor: &Monitor,
+) -> Result<Array1<T>, StretchError>
 where
     T: FftNum + AudioSample,
 {
+    FrameConfig::new(window_size, hop_length)?;
+    check_window(window, window_size)?;
+
+    let required = signal.nrows().saturating_sub(1) * hop_length + window_size;
+    if num_samples < required {
+        return Err(StretchError::InvalidParameter(format!(
+            "{num_samples} samples cannot hold {} frames, which need {required}", signal.nrows(),
+        )));
+    }
+
     let mut samples = Array1::from_elem(num_samples, T::zero());
     let mut weights = Array1::from_elem(num_samples, T::zero());
 
@@ -88,7 +163,19 @@ where
         
         let mut win_samples = samples.slice_mut(s![start..start + window_size]);
         win_samples += &window_samples;
+
+        monitor.frame(Stage::Istft, i + 1, signal.nrows())?;
+    }
+
+    Ok(samples / weights.mapv(|v| if v == T::zero() { T::one() } else { v }))
+}
+
+fn check_window<T>(window: &Array1<T>, window_size: usize) -> Result<(), StretchError> {
+    if window.len() < window_size {
+        return Err(StretchError::InvalidParameter(format!(
+            "the window has {} samples but the window size is {window_size}", window.len(),
+        )));
     }
 
-    samples / weights.mapv(|v| if v == T::zero() { T::one() } else { v })
+    Ok(())
 }
@@ -0,0 +1,495 @@
+use std::io::{self, Read, Write};
+
+use claxon::FlacReader;
+
+use crate::{
+    downmix::speaker,
+    signal::{ChannelLayout, Metadata},
+};
+
+/// The number of frames per FLAC block, except for the last one.
+const BLOCK_SIZE: usize = 4096;
+
+/// The highest sample rate of a FLAC stream, in Hz.
+pub const MAX_SAMPLE_RATE: u32 = 655_350;
+
+/// The sample widths written, which are those that frame headers can name rather than leave to
+/// STREAMINFO, as not every decoder looks them up there.
+pub const BITS_PER_SAMPLE: [u16; 5] = [8, 12, 16, 20, 24];
+
+/// The most channels a FLAC stream can hold.
+pub const MAX_CHANNELS: usize = 8;
+
+/// The largest partition order searched for the residual.
+const MAX_PARTITION_ORDER: u32 = 8;
+
+/// The Vorbis comment holding the channel mask of layouts other than the default for their
+/// channel count, as written by the reference encoder.
+const CHANNEL_MASK_TAG: &str = "WAVEFORMATEXTENSIBLE_CHANNEL_MASK";
+
+/// The speakers FLAC assigns to 3 to 8 channels when no channel mask is given.
+fn default_mask(channels: usize) -> Option<u32> {
+    use speaker::*;
+
+    match channels {
+        3 => Some(FRONT_LEFT | FRONT_RIGHT | FRONT_CENTER),
+        4 => Some(FRONT_LEFT | FRONT_RIGHT | BACK_LEFT | BACK_RIGHT),
+        5 => Some(FRONT_LEFT | FRONT_RIGHT | FRONT_CENTER | BACK_LEFT | BACK_RIGHT),
+        6 => Some(LAYOUT_5_1),
+        7 => Some(LAYOUT_5_1_SIDE | BACK_CENTER),
+        8 => Some(LAYOUT_7_1),
+        _ => None,
+    }
+}
+
+/// The tags of a signal with their Vorbis comment field names.
+fn tags(metadata: &Metadata) -> [(&'static str, &Option<String>); 7] {
+    [
+        ("TITLE", &metadata.title),
+        ("ARTIST", &metadata.artist),
+        ("ALBUM", &metadata.album),
+        ("DATE", &metadata.date),
+        ("GENRE", &metadata.genre),
+        ("COMMENT", &metadata.comment),
+        ("ENCODER", &metadata.software),
+    ]
+}
+
+/// Read the Vorbis comments of a FLAC stream that have a [`Metadata`] field, taking the first
+/// value of repeated fields.
+pub(crate) fn read_metadata<R: Read>(reader: &FlacReader<R>) -> Metadata {
+    let mut metadata = Metadata::default();
+    let first = |names: &[&str]| names.iter().find_map(|&name| reader.get_tag(name).next()).map(str::to_string);
+
+    metadata.title = first(&["TITLE"]);
+    metadata.artist = first(&["ARTIST"]);
+    metadata.album = first(&["ALBUM"]);
+    metadata.date = first(&["DATE"]);
+    metadata.genre = first(&["GENRE"]);
+    metadata.comment = first(&["COMMENT", "DESCRIPTION"]);
+    metadata.software = first(&["ENCODER"]);
+    metadata
+}
+
+/// The speaker positions of a FLAC stream, from its channel mask tag or the default order of its
+/// channel count.
+pub(crate) fn read_channel_mask<R: Read>(reader: &FlacReader<R>) -> Option<u32> {
+    let tagged = reader.get_tag(CHANNEL_MASK_TAG).next().and_then(|mask| {
+        let mask = mask.trim();
+        match mask.strip_prefix("0x").or_else(|| mask.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => mask.parse().ok(),
+        }
+    });
+
+    tagged.or_else(|| default_mask(reader.streaminfo().channels as usize))
+}
+
+/// Decode the remaining blocks of a FLAC stream into interleaved samples.
+pub(crate) fn read_interleaved<R: Read>(reader: &mut FlacReader<R>) -> Result<Vec<i32>, claxon::Error> {
+    let info = reader.streaminfo();
+    let mut samples = Vec::with_capacity(info.samples.unwrap_or(0) as usize * info.channels as usize);
+
+    let mut blocks = reader.blocks();
+    let mut buffer = Vec::new();
+    while let Some(block) = blocks.read_next_or_eof(buffer)? {
+        for frame in 0..block.duration() {
+            samples.extend((0..block.channels()).map(|channel| block.sample(channel, frame)));
+        }
+        buffer = block.into_buffer();
+    }
+
+    Ok(samples)
+}
+
+/// Encode interleaved integer `samples` of `bits_per_sample` bits, one of [`BITS_PER_SAMPLE`], as a FLAC stream with the channels of `layout` and the tags of `metadata`.
+///
+/// Every block is predicted with the best of the fixed polynomial predictors, and stereo blocks
+/// with the best of the left/right, left/side, side/right and mid/side channel pairs.
+pub fn encode<W: Write>(
+    mut writer: W,
+    samples: &[i32],
+    layout: ChannelLayout,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    metadata: &Metadata,
+) -> io::Result<W> {
+    let channels = layout.channels();
+    let frames = samples.len() / channels.max(1);
+
+    let blocks = samples[..frames * channels].chunks(BLOCK_SIZE * channels.max(1))
+        .enumerate()
+        .map(|(number, block)| encode_block(block, channels, number as u64, sample_rate, bits_per_sample))
+        .collect::<Vec<_>>();
+
+    let mut info = BitWriter::default();
+    info.write(BLOCK_SIZE as u64, 16);
+    info.write(BLOCK_SIZE as u64, 16);
+    info.write(blocks.iter().map(Vec::len).min().unwrap_or(0) as u64, 24);
+    info.write(blocks.iter().map(Vec::len).max().unwrap_or(0) as u64, 24);
+    info.write(sample_rate as u64, 20);
+    info.write(channels.saturating_sub(1) as u64, 3);
+    info.write(bits_per_sample as u64 - 1, 5);
+    info.write(frames as u64, 36);
+    // The MD5 signature of the samples is left out, which zeros mark as unknown.
+    info.write(0, 64);
+    info.write(0, 64);
+
+    writer.write_all(b"fLaC")?;
+    write_metadata_block(&mut writer, 0, false, &info.into_bytes())?;
+    write_metadata_block(&mut writer, 4, true, &vorbis_comment(layout, metadata))?;
+    for block in blocks {
+        writer.write_all(&block)?;
+    }
+
+    Ok(writer)
+}
+
+fn write_metadata_block<W: Write>(writer: &mut W, block_type: u8, last: bool, body: &[u8]) -> io::Result<()> {
+    let len = body.len() as u32;
+    writer.write_all(&[(last as u8) << 7 | block_type])?;
+    writer.write_all(&len.to_be_bytes()[1..])?;
+    writer.write_all(body)
+}
+
+/// The body of a Vorbis comment block with the tags of `metadata`, and the channel mask of
+/// `layout` if it differs from the default.
+fn vorbis_comment(layout: ChannelLayout, metadata: &Metadata) -> Vec<u8> {
+    let mut comments = tags(metadata).into_iter()
+        .filter_map(|(name, value)| value.as_ref().map(|value| format!("{name}={value}")))
+        .collect::<Vec<_>>();
+    if let Some(mask) = layout.mask().filter(|&mask| Some(mask) != default_mask(layout.channels())) {
+        comments.push(format!("{CHANNEL_MASK_TAG}=0x{mask:04X}"));
+    }
+
+    let vendor = env!("CARGO_PKG_NAME");
+    let mut body = Vec::new();
+    body.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    body.extend_from_slice(vendor.as_bytes());
+    body.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        body.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        body.extend_from_slice(comment.as_bytes());
+    }
+    body
+}
+
+/// Encode one block of interleaved samples as a frame.
+fn encode_block(block: &[i32], channels: usize, number: u64, sample_rate: u32, bits_per_sample: u16) -> Vec<u8> {
+    let len = block.len() / channels;
+    let bits = bits_per_sample as u32;
+    let channel = |c: usize| block.iter().skip(c).step_by(channels).map(|&s| s as i64).collect::<Vec<_>>();
+
+    // The channel assignment and the subframes it stores.
+    let (assignment, subframes) = if channels == 2 {
+        let (left, right) = (channel(0), channel(1));
+        let side = left.iter().zip(&right).map(|(l, r)| l - r).collect::<Vec<_>>();
+        let mid = left.iter().zip(&right).map(|(l, r)| (l + r) >> 1).collect::<Vec<_>>();
+
+        let left = Subframe::plan(left, bits);
+        let right = Subframe::plan(right, bits);
+        let side = Subframe::plan(side, bits + 1);
+        let mid = Subframe::plan(mid, bits);
+
+        let costs = [left.cost + right.cost, left.cost + side.cost, side.cost + right.cost, mid.cost + side.cost];
+        match (0..4).min_by_key(|&i| costs[i]).unwrap() {
+            0 => (1, vec![left, right]),
+            1 => (8, vec![left, side]),
+            2 => (9, vec![side, right]),
+            _ => (10, vec![mid, side]),
+        }
+    } else {
+        (channels as u64 - 1, (0..channels).map(|c| Subframe::plan(channel(c), bits)).collect())
+    };
+
+    let mut frame = BitWriter::default();
+    frame.write(0xFFF8, 16);
+    match len {
+        BLOCK_SIZE => frame.write(0b1100, 4),
+        1..=256 => frame.write(0b0110, 4),
+        _ => frame.write(0b0111, 4),
+    }
+    let (rate_code, rate_bits, rate) = sample_rate_code(sample_rate);
+    frame.write(rate_code, 4);
+    frame.write(assignment, 4);
+    frame.write(match bits {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        _ => 0b110,
+    }, 3);
+    frame.write(0, 1);
+    frame.write_utf8(number);
+    match len {
+        BLOCK_SIZE => {},
+        1..=256 => frame.write(len as u64 - 1, 8),
+        _ => frame.write(len as u64 - 1, 16),
+    }
+    frame.write(rate, rate_bits);
+    let crc = crc8(frame.bytes());
+    frame.write(crc as u64, 8);
+
+    for subframe in &subframes {
+        subframe.encode(&mut frame);
+    }
+    frame.align();
+    let crc = crc16(frame.bytes());
+    frame.write(crc as u64, 16);
+
+    frame.into_bytes()
+}
+
+/// The frame header code of `sample_rate`, and the width and value of the rate stored after the
+/// header when it has no code of its own.
+fn sample_rate_code(sample_rate: u32) -> (u64, u32, u64) {
+    const CODES: [(u32, u64); 11] = [
+        (88_200, 0b0001),
+        (176_400, 0b0010),
+        (192_000, 0b0011),
+        (8_000, 0b0100),
+        (16_000, 0b0101),
+        (22_050, 0b0110),
+        (24_000, 0b0111),
+        (32_000, 0b1000),
+        (44_100, 0b1001),
+        (48_000, 0b1010),
+        (96_000, 0b1011),
+    ];
+
+    let rate = sample_rate as u64;
+    match CODES.iter().find(|&&(r, _)| r == sample_rate) {
+        Some(&(_, code)) => (code, 0, 0),
+        None if rate.is_multiple_of(1000) && rate / 1000 < 256 => (0b1100, 8, rate / 1000),
+        None if rate < 65536 => (0b1101, 16, rate),
+        None if rate.is_multiple_of(10) && rate / 10 < 65536 => (0b1110, 16, rate / 10),
+        // Taken from the stream info.
+        None => (0b0000, 0, 0),
+    }
+}
+
+/// How one channel of a block is encoded, with its size in bits.
+struct Subframe {
+    samples: Vec<i64>,
+    bits: u32,
+    kind: SubframeKind,
+    cost: u64,
+}
+
+enum SubframeKind {
+    Constant,
+    Verbatim,
+    Fixed { order: usize, partition_order: u32, parameters: Vec<u32> },
+}
+
+impl Subframe {
+    /// Choose the smallest encoding of `samples` of `bits` bits.
+    fn plan(samples: Vec<i64>, bits: u32) -> Self {
+        const HEADER: u64 = 8;
+
+        if samples.iter().all(|&s| s == samples[0]) {
+            return Self { samples, bits, kind: SubframeKind::Constant, cost: HEADER + bits as u64 };
+        }
+
+        let mut best = (SubframeKind::Verbatim, HEADER + samples.len() as u64 * bits as u64);
+        let mut residual = samples.clone();
+
+        for order in 0..=4.min(samples.len() - 1) {
+            if order > 0 {
+                // Each order's residual is the difference of the previous order's.
+                for i in (order..samples.len()).rev() {
+                    residual[i] -= residual[i - 1];
+                }
+            }
+
+            let (partition_order, parameters, cost) = rice_partitions(&residual[order..], samples.len(), order);
+            let cost = HEADER + order as u64 * bits as u64 + cost;
+            if cost < best.1 {
+                best = (SubframeKind::Fixed { order, partition_order, parameters }, cost);
+            }
+        }
+
+        Self { samples, bits, kind: best.0, cost: best.1 }
+    }
+
+    fn encode(&self, writer: &mut BitWriter) {
+        match &self.kind {
+            SubframeKind::Constant => {
+                writer.write(0b0000_0000, 8);
+                writer.write_signed(self.samples[0], self.bits);
+            },
+            SubframeKind::Verbatim => {
+                writer.write(0b0000_0010, 8);
+                for &sample in &self.samples {
+                    writer.write_signed(sample, self.bits);
+                }
+            },
+            &SubframeKind::Fixed { order, partition_order, ref parameters } => {
+                writer.write(0b0001_0000 | (order as u64) << 1, 8);
+                for &sample in &self.samples[..order] {
+                    writer.write_signed(sample, self.bits);
+                }
+
+                let mut residual = self.samples.clone();
+                for o in 1..=order {
+                    for i in (o..residual.len()).rev() {
+                        residual[i] -= residual[i - 1];
+                    }
+                }
+
+                let wide = parameters.iter().any(|&k| k > 14);
+                writer.write(wide as u64, 2);
+                writer.write(partition_order as u64, 4);
+
+                let partition_len = self.samples.len() >> partition_order;
+                let mut start = order;
+                for (p, &k) in parameters.iter().enumerate() {
+                    let end = (p + 1) * partition_len;
+                    writer.write(k as u64, if wide { 5 } else { 4 });
+                    for &r in &residual[start..end] {
+                        writer.write_rice(r, k);
+                    }
+                    start = end;
+                }
+            },
+        }
+    }
+}
+
+/// Split the `residual` of a block of `len` samples, which skips the first `order` warm-up
+/// samples, into the partitions whose Rice codes are shortest, returning the partition order,
+/// the Rice parameter of every partition and the size in bits.
+fn rice_partitions(residual: &[i64], len: usize, order: usize) -> (u32, Vec<u32>, u64) {
+    let folded = residual.iter().map(|&r| ((r << 1) ^ (r >> 63)) as u64).collect::<Vec<_>>();
+
+    let mut best: Option<(u32, Vec<u32>, u64)> = None;
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partitions = 1 << partition_order;
+        let partition_len = len >> partition_order;
+        if !len.is_multiple_of(partitions) || partition_len < order {
+            break;
+        }
+
+        let mut parameters = Vec::with_capacity(partitions);
+        let mut cost = 2 + 4;
+        let mut start = 0;
+        for p in 0..partitions {
+            let end = (p + 1) * partition_len - order;
+            let (k, bits) = rice_parameter(&folded[start..end]);
+            parameters.push(k);
+            cost += bits;
+            start = end;
+        }
+        if parameters.iter().any(|&k| k > 14) {
+            cost += partitions as u64;
+        }
+
+        if best.as_ref().is_none_or(|best| cost < best.2) {
+            best = Some((partition_order, parameters, cost));
+        }
+    }
+
+    best.expect("partition order 0 always fits")
+}
+
+/// The Rice parameter that codes the zigzag-`folded` values in the fewest bits, estimated from
+/// their sum, with the size of the partition including its parameter.
+fn rice_parameter(folded: &[u64]) -> (u32, u64) {
+    const MAX_PARAMETER: u32 = 30;
+
+    let len = folded.len() as u64;
+    let sum: u64 = folded.iter().sum();
+
+    (0..=MAX_PARAMETER)
+        .map(|k| (k, 4 + len * (k as u64 + 1) + (sum >> k)))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap()
+}
+
+/// Writes values of any width up to 36 bits, most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits > 32 {
+            self.write(value >> 32, bits - 32);
+            self.write(value & 0xFFFF_FFFF, 32);
+            return;
+        }
+
+        self.pending = (self.pending << bits) | (value & ((1 << bits) - 1));
+        self.pending_bits += bits;
+        while self.pending_bits >= 8 {
+            self.pending_bits -= 8;
+            self.bytes.push((self.pending >> self.pending_bits) as u8);
+        }
+        self.pending &= (1 << self.pending_bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros as u32 + 1);
+    }
+
+    fn write_rice(&mut self, value: i64, parameter: u32) {
+        let folded = ((value << 1) ^ (value >> 63)) as u64;
+        self.write_unary(folded >> parameter);
+        self.write(folded, parameter);
+    }
+
+    /// Write a frame number in the extended UTF-8 coding of FLAC headers.
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+
+        // A lead byte of one bit per byte of the code, then six bits per continuation byte.
+        let continuation = (1..6).find(|&n| value < 1 << (5 * n + 6)).unwrap_or(6);
+        self.write((0xFF << (7 - continuation)) & 0xFF | value >> (6 * continuation), 8);
+        for n in (0..continuation).rev() {
+            self.write(0x80 | (value >> (6 * n)) & 0x3F, 8);
+        }
+    }
+
+    /// Pad with zeros to a whole byte.
+    fn align(&mut self) {
+        if self.pending_bits > 0 {
+            self.write(0, 8 - self.pending_bits);
+        }
+    }
+
+    /// The whole bytes written so far.
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 })
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ (byte as u16) << 8, |crc, _| if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 })
+    })
+}
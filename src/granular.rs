@@ -0,0 +1,161 @@
+use crate::{
+    progress::{Monitor, Stage},
+    rng::Rng,
+    sample::{AudioSample, ConvertSample},
+    signal::TimeDomainSignal,
+    stretcher::{check_input, check_ratio, fit, StretchConfig, StretchError, TimeStretcher, MAX_WINDOW_SIZE, MIN_WINDOW_SIZE},
+    windows::{build_window, WindowFn},
+};
+
+/// Parameters of the [`granular`] engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GranularParams {
+    /// The length of each grain, in samples.
+    pub grain_size: usize,
+    /// The average number of grains overlapping at any point of the output.
+    pub density: f32,
+    /// The maximum random offset of each grain's read position from the playhead, in samples.
+    pub position_jitter: usize,
+    /// The maximum random detuning of each grain, in cents.
+    pub pitch_jitter: f32,
+    /// The seed of the random number generator driving the jitter.
+    pub seed: u64,
+}
+
+impl GranularParams {
+    /// Check that the grain size is in range and that the density and jitter are usable.
+    pub fn validate(&self) -> Result<(), StretchError> {
+        if !(MIN_WINDOW_SIZE..=MAX_WINDOW_SIZE).contains(&self.grain_size) {
+            return Err(StretchError::WindowSize { window_size: self.grain_size });
+        }
+
+        check_ratio("density", self.density)?;
+
+        if !self.pitch_jitter.is_finite() || self.pitch_jitter < 0.0 {
+            return Err(StretchError::InvalidParameter(format!(
+                "the pitch jitter must be finite and non-negative, got {}", self.pitch_jitter,
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for GranularParams {
+    fn default() -> Self {
+        Self {
+            grain_size: 2048,
+            density: 4.0,
+            position_jitter: 0,
+            pitch_jitter: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Granular synthesis
+///
+/// Windowed grains are read from the signal around a playhead that moves at `1 / scale_factor`
+/// of the output rate, resampled by `pitch_factor` and scattered over the output.
+pub fn granular<T, F>(
+    signal: TimeDomainSignal<T>,
+    scale_factor: f32,
+    pitch_factor: f32,
+    params: &GranularParams,
+    window_fn: F,
+    monitor: &Monitor,
+) -> Result<TimeDomainSignal<T>, StretchError>
+where
+    T: AudioSample,
+    F: Fn(f32, usize) -> T,
+    f32: ConvertSample<T>,
+{
+    params.validate()?;
+    check_ratio("scale factor", scale_factor)?;
+    check_ratio("pitch factor", pitch_factor)?;
+    check_input(&signal)?;
+
+    let synth_len = (signal.len() as f32 * scale_factor).ceil() as usize;
+    let mut synth_signal = vec![0f32; synth_len];
+
+    let window = build_window(window_fn, params.grain_size).mapv(|v| ConvertSample::<f32>::convert_sample(v));
+    let window_mean = window.sum() / window.len() as f32;
+    let gain = 1.0 / (params.density * window_mean).max(f32::EPSILON);
+
+    let input: Vec<f32> = signal.iter().map(|&s| s.convert_sample()).collect();
+    let grain_interval = params.grain_size as f32 / params.density;
+    let grains = (synth_len as f32 / grain_interval).ceil() as usize;
+    let mut rng = Rng::new(params.seed);
+
+    for k in 0..grains {
+        let t = k as f32 * grain_interval;
+
+        let start = t.round() as usize;
+        let position = t / scale_factor + params.position_jitter as f32 * rng.next_signed();
+        let ratio = pitch_factor * f32::exp2(params.pitch_jitter * rng.next_signed() / 1200.0);
+
+        for (n, &w) in window.iter().enumerate() {
+            let Some(out) = synth_signal.get_mut(start + n) else {
+                break;
+            };
+
+            *out += w * sample_linear(&input, position + n as f32 * ratio);
+        }
+
+        monitor.frame(Stage::Granular, k + 1, grains)?;
+    }
+
+    Ok(synth_signal.into_iter().map(|s| (s * gain).convert_sample()).collect())
+}
+
+/// [`granular`] as a [`TimeStretcher`].
+#[derive(Debug, Clone)]
+pub struct Granular {
+    pub params: GranularParams,
+    pub window_fn: WindowFn,
+}
+
+impl TimeStretcher for Granular {
+    fn process_monitored(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<TimeDomainSignal<f32>, StretchError> {
+        self.process_measured(signal, config, monitor).map(|(processed, _)| processed)
+    }
+
+    fn process_measured(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<(TimeDomainSignal<f32>, usize), StretchError> {
+        let output_len = self.output_len(signal.len(), config);
+
+        let mut synth_signal = granular(signal, config.stretch, config.pitch, &self.params, self.window_fn, monitor)?;
+        let produced_len = synth_signal.len();
+        fit(&mut synth_signal, output_len);
+        Ok((synth_signal, produced_len))
+    }
+
+    fn latency(&self) -> usize {
+        self.params.grain_size
+    }
+}
+
+/// Sample `signal` at the fractional index `x` with linear interpolation, treating everything
+/// outside of the signal as silence.
+fn sample_linear(signal: &[f32], x: f32) -> f32 {
+    if x < 0.0 {
+        return 0.0;
+    }
+
+    let i = x.floor() as usize;
+    let d = x - x.floor();
+
+    let s0 = signal.get(i).copied().unwrap_or(0.0);
+    let s1 = signal.get(i + 1).copied().unwrap_or(0.0);
+
+    s0 + (s1 - s0) * d
+}
@@ -0,0 +1,35 @@
+//! An exploration of several different audio pitch shifting methods.
+//!
+//! Every algorithm is available both as a free function operating on a [`TimeDomainSignal`] and
+//! as a [`TimeStretcher`], so that algorithms can be selected at runtime.
+
+pub mod signal;
+pub mod flac;
+mod aiff;
+pub mod raw;
+mod compressed;
+pub mod dither;
+pub mod downmix;
+pub mod sample;
+pub mod merge;
+pub mod ola;
+pub mod sola;
+pub mod phase_vocoder;
+pub mod granular;
+pub mod windows;
+pub mod fft;
+pub mod crossfade;
+pub mod resample;
+pub mod stretcher;
+pub mod progress;
+pub mod preset;
+pub mod units;
+pub mod streaming;
+pub mod realtime;
+pub mod analysis;
+
+pub mod rng;
+
+pub use signal::{read, read_mono, read_mono_from, write, write_to, ChannelLayout, Metadata, MultiChannelSignal, Signal, TimeDomainSignal};
+pub use progress::{CancellationToken, Monitor};
+pub use stretcher::{FrameConfig, StretchConfig, StretchError, TimeStretcher};
@@ -1,35 +1,32 @@
-use signal::TimeDomainSignal;
+use std::{io::ErrorKind, process::ExitCode};
 
-mod signal;
-mod sample;
-mod merge;
-mod ola;
-mod sola;
-mod phase_vocoder;
-mod windows;
-mod fft;
+use clap::Parser;
 
-const WINDOW_SIZE_MS: f32 = 20.0;
-const HOP_LENGTH_MS: f32 = 8.0;
+use crate::cli::{Cli, CliError, Command};
 
-fn main() {
-    let (signal, sample_rate): (TimeDomainSignal<f32>, u32) = signal::read_mono("input/powerhse.wav").unwrap();
+mod cli;
+mod commands;
 
-    let window_size = (sample_rate as f32 * WINDOW_SIZE_MS / 1000.0) as usize;
-    let hop_length = (sample_rate as f32 * HOP_LENGTH_MS / 1000.0) as usize;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-    let stretched = phase_vocoder::phase_vocoder(
-        signal,
-        2.0,
-        // 8192,
-        // 2048,
-        4096,
-        1024,
-        0.25,
-        // window_size,
-        // hop_length,
-        windows::hann_window,
-    );
+    let result = match &cli.command {
+        Command::Stretch(args) => commands::stretch(args),
+        Command::Shift(args) => commands::shift(args),
+        Command::Analyze(args) => commands::analyze(args),
+        Command::Info(args) => commands::info(args),
+        Command::Batch(args) => commands::batch(args),
+        Command::Compare(args) => commands::compare(args),
+        Command::Sweep(args) => commands::sweep(args),
+    };
 
-    signal::write(stretched, sample_rate, "output/powerhse.wav").unwrap();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // A reader such as `head` closing the pipe early is not a failure.
+        Err(CliError::Stdout(e)) if e.kind() == ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        },
+    }
 }
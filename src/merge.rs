@@ -1,17 +1,41 @@
-use crate::{sample::AudioSample, signal::TimeDomainSignal};
+use ndarray::Zip;
+use thiserror::Error;
 
-/// Merge two [`TimeDomainSignal`]s into one by applying the given function `f` to each sample.
-///
-/// # Panics
+use crate::{sample::AudioSample, signal::Signal};
+
+/// Merge two [`Signal`]s into one by applying the given function `f` to each pair of samples.
 ///
-/// Panics if the signals' sample rates or number of samples do not match.
-pub fn merge<T, U, V, F>(a: TimeDomainSignal<T>, b: TimeDomainSignal<U>, f: F) -> TimeDomainSignal<V>
+/// The signals must have the same sample rate, channel layout and length. The result keeps the
+/// metadata of `a`.
+pub fn merge<T, U, V, F>(a: Signal<T>, b: Signal<U>, f: F) -> Result<Signal<V>, MergeError>
 where
     T: AudioSample,
     U: AudioSample,
     V: AudioSample,
     F: Fn(T, U) -> V,
 {
-    assert!(a.len() == b.len() && a.len() == b.len());
-    a.into_iter().zip(b.into_iter()).map(|(s, t)| f(s, t)).collect()
+    if a.sample_rate() != b.sample_rate() {
+        return Err(MergeError::SampleRateMismatch(a.sample_rate(), b.sample_rate()));
+    }
+
+    if a.layout() != b.layout() {
+        return Err(MergeError::LayoutMismatch(a.channels(), b.channels()));
+    }
+
+    if a.frames() != b.frames() {
+        return Err(MergeError::LengthMismatch(a.frames(), b.frames()));
+    }
+
+    let merged = Zip::from(a.samples()).and(b.samples()).map_collect(|&s, &t| f(s, t));
+    Ok(a.with_samples(merged).expect("same layout"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MergeError {
+    #[error("cannot merge signals sampled at {0} Hz and {1} Hz")]
+    SampleRateMismatch(u32, u32),
+    #[error("cannot merge signals with different channel layouts ({0} and {1} channels)")]
+    LayoutMismatch(usize, usize),
+    #[error("cannot merge signals of {0} and {1} samples")]
+    LengthMismatch(usize, usize),
 }
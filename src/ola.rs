@@ -1,6 +1,13 @@
 use ndarray::s;
 
-use crate::{sample::AudioSample, signal::TimeDomainSignal, windows::build_window};
+use crate::{
+    crossfade::{splice, Crossfade},
+    progress::{Monitor, Stage},
+    sample::{AudioSample, ConvertSample},
+    signal::TimeDomainSignal,
+    stretcher::{check_input, check_ratio, stretch_and_resample, FrameConfig, StretchConfig, StretchError, TimeStretcher},
+    windows::{build_window, WindowFn},
+};
 
 /// Overlap Add
 pub fn ola<T, F>(
@@ -9,17 +16,22 @@ pub fn ola<T, F>(
     window_size: usize,
     hop_length: usize,
     window_fn: F,
-) -> TimeDomainSignal<T>
+    monitor: &Monitor,
+) -> Result<TimeDomainSignal<T>, StretchError>
 where
     T: AudioSample,
     F: Fn(f32, usize) -> T, 
 {
-    let synth_hop_length = (hop_length as f32 * scale_factor) as usize;
-    let synth_len = (signal.len() as f32 / hop_length as f32).ceil() as usize * synth_hop_length + window_size;
+    FrameConfig::new(window_size, hop_length)?;
+    check_ratio("scale factor", scale_factor)?;
+    check_input(&signal)?;
+
+    let synth_len = (signal.len() as f32 * scale_factor).ceil() as usize + window_size;
     let mut synth_signal = TimeDomainSignal::from_elem(synth_len, T::zero());
     let mut synth_weights = TimeDomainSignal::from_elem(synth_len, T::zero());
 
     let window = build_window(window_fn, window_size);
+    let frames = signal.len().div_ceil(hop_length);
 
     for i in (0..signal.len()).step_by(hop_length) {
         let len = window_size.min(signal.len() - i);
@@ -33,7 +45,87 @@ where
 
         let mut window_weights = synth_weights.slice_mut(s![index..index + len]);
         window_weights += &window.slice(s![..len]);
+
+        monitor.frame(Stage::Ola, i / hop_length + 1, frames)?;
+    }
+
+    Ok(synth_signal / synth_weights.mapv(|v| if v == T::zero() { T::one() } else { v }))
+}
+
+/// Overlap Add, splicing unwindowed segments together with an explicit `crossfade` instead of
+/// normalizing by the summed window weights.
+pub fn ola_crossfade<T>(
+    signal: TimeDomainSignal<T>,
+    scale_factor: f32,
+    hop_length: usize,
+    crossfade: Crossfade,
+    monitor: &Monitor,
+) -> Result<TimeDomainSignal<T>, StretchError>
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    if hop_length == 0 {
+        return Err(StretchError::ZeroHop);
+    }
+    check_ratio("scale factor", scale_factor)?;
+    check_input(&signal)?;
+
+    let synth_hop_length = ((hop_length as f32 * scale_factor) as usize).max(1);
+    let segment_len = synth_hop_length + crossfade.overlap;
+    let synth_len = (signal.len() as f32 * scale_factor).ceil() as usize + segment_len;
+    let mut synth_signal = TimeDomainSignal::from_elem(synth_len, T::zero());
+
+    let frames = signal.len().div_ceil(hop_length);
+    let mut written = 0;
+    for i in (0..signal.len()).step_by(hop_length) {
+        let len = segment_len.min(signal.len() - i);
+        let index = (i as f32 * scale_factor) as usize;
+
+        written = splice(synth_signal.view_mut(), signal.slice(s![i..i + len]), index, written, &crossfade);
+
+        monitor.frame(Stage::Ola, i / hop_length + 1, frames)?;
     }
 
-    synth_signal / synth_weights.mapv(|v| if v == T::zero() { T::one() } else { v })
+    Ok(synth_signal.slice_move(s![..written]))
+}
+
+/// [`ola`] and [`ola_crossfade`] as a [`TimeStretcher`].
+#[derive(Debug, Clone)]
+pub struct Ola {
+    pub frames: FrameConfig,
+    pub window_fn: WindowFn,
+    /// Splice segments together with this crossfade instead of normalizing by the summed window
+    /// weights.
+    pub crossfade: Option<Crossfade>,
+}
+
+impl TimeStretcher for Ola {
+    fn process_monitored(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<TimeDomainSignal<f32>, StretchError> {
+        self.process_measured(signal, config, monitor).map(|(processed, _)| processed)
+    }
+
+    fn process_measured(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<(TimeDomainSignal<f32>, usize), StretchError> {
+        let output_len = self.output_len(signal.len(), config);
+        let (window_size, hop_length) = (self.frames.window_size(), self.frames.hop_length());
+
+        stretch_and_resample(signal, config, output_len, |signal, scale_factor| match self.crossfade {
+            Some(crossfade) => ola_crossfade(signal, scale_factor, hop_length, crossfade, monitor),
+            None => ola(signal, scale_factor, window_size, hop_length, self.window_fn, monitor),
+        })
+    }
+
+    fn latency(&self) -> usize {
+        self.frames.window_size()
+    }
 }
@@ -2,7 +2,14 @@ use ndarray::{s, Array2, ArrayView2, ArrayViewMut2, Axis, Zip};
 use num_complex::{Complex, ComplexFloat};
 use rustfft::FftNum;
 
-use crate::{fft::{istft, stft}, sample::AudioSample, signal::TimeDomainSignal, windows::build_window};
+use crate::{
+    fft::{istft, stft},
+    progress::{Monitor, Stage},
+    sample::AudioSample,
+    signal::TimeDomainSignal,
+    stretcher::{check_input, check_ratio, stretch_and_resample, FrameConfig, StretchConfig, StretchError, TimeStretcher},
+    windows::{build_window, WindowFn},
+};
 
 pub fn phase_vocoder<F>(
     signal: TimeDomainSignal<f32>,
@@ -11,11 +18,16 @@ pub fn phase_vocoder<F>(
     hop_length: usize,
     transient_cutoff: f32,
     window_fn: F,
-) -> TimeDomainSignal<f32>
+    monitor: &Monitor,
+) -> Result<TimeDomainSignal<f32>, StretchError>
 where
     f32: AudioSample + FftNum,
     F: Fn(f32, usize) -> f32
 {
+    FrameConfig::new(window_size, hop_length)?;
+    check_ratio("scale factor", scale_factor)?;
+    check_input(&signal)?;
+
     // Compute the number of frames in the original STFT.
     let frames = signal.len().div_ceil(hop_length);
     // Compute the number of frames in the synthesized STFT.
@@ -24,7 +36,7 @@ where
     // Build the window function used in the STFT and inverse STFT.
     let window = build_window(window_fn, window_size);
     // Compute the original signal's STFT.
-    let stft = stft(&signal, window_size, hop_length, &window);
+    let stft = stft(&signal, window_size, hop_length, &window, monitor)?;
 
     // Extract the magnitudes and phases from the complex output of the STFT.
     let mags = stft.mapv(|v| v.abs());
@@ -65,6 +77,8 @@ where
         let mut new_phase = freq_phase * &transient + time_phase * (1.0 - transient);
         new_phase.map_inplace(|v| *v = v.rem_euclid(std::f32::consts::TAU));
         shifted_phases.slice_mut(s![t, ..]).assign(&new_phase);
+
+        monitor.frame(Stage::PhaseAccumulation, t, synth_frames - 1)?;
     }
 
     // Synthesize the new STFT by converting phase and magnitude back to cartesian coordinates.
@@ -75,7 +89,44 @@ where
         }
     });
 
-    istft(synth_stft, window_size, hop_length, synth_frames * hop_length + window_size, &window)
+    istft(synth_stft, window_size, hop_length, synth_frames * hop_length + window_size, &window, monitor)
+}
+
+/// [`phase_vocoder`] as a [`TimeStretcher`].
+#[derive(Debug, Clone)]
+pub struct PhaseVocoder {
+    pub frames: FrameConfig,
+    pub transient_cutoff: f32,
+    pub window_fn: WindowFn,
+}
+
+impl TimeStretcher for PhaseVocoder {
+    fn process_monitored(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<TimeDomainSignal<f32>, StretchError> {
+        self.process_measured(signal, config, monitor).map(|(processed, _)| processed)
+    }
+
+    fn process_measured(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<(TimeDomainSignal<f32>, usize), StretchError> {
+        let output_len = self.output_len(signal.len(), config);
+        let (window_size, hop_length) = (self.frames.window_size(), self.frames.hop_length());
+
+        stretch_and_resample(signal, config, output_len, |signal, scale_factor| {
+            phase_vocoder(signal, scale_factor, window_size, hop_length, self.transient_cutoff, self.window_fn, monitor)
+        })
+    }
+
+    fn latency(&self) -> usize {
+        self.frames.window_size()
+    }
 }
 
 /// Perform linear interpolation on a component of the STFT along the time axis, 
@@ -0,0 +1,285 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    crossfade::{Crossfade, CrossfadeShape},
+    granular::{Granular, GranularParams},
+    ola::Ola,
+    phase_vocoder::PhaseVocoder,
+    sola::Sola,
+    stretcher::{StretchConfig, StretchError, TimeStretcher},
+    units::{FrameSpec, Length},
+    windows::WindowFunction,
+};
+
+/// The parameters of [`Ola`] and [`Sola`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverlapAddParams {
+    #[serde(default)]
+    pub window: WindowFunction,
+    #[serde(flatten)]
+    pub frames: FrameSpec,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crossfade: Option<CrossfadeParams>,
+}
+
+/// The parameters of a [`Crossfade`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrossfadeParams {
+    pub shape: CrossfadeShape,
+    pub overlap: Length,
+}
+
+/// The parameters of [`PhaseVocoder`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseVocoderParams {
+    #[serde(default)]
+    pub window: WindowFunction,
+    #[serde(flatten)]
+    pub frames: FrameSpec,
+    pub transient_cutoff: f32,
+}
+
+/// The parameters of [`Granular`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrainParams {
+    #[serde(default)]
+    pub window: WindowFunction,
+    pub grain_size: Length,
+    pub density: f32,
+    #[serde(default = "no_jitter")]
+    pub position_jitter: Length,
+    #[serde(default)]
+    pub pitch_jitter: f32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn no_jitter() -> Length {
+    Length::Samples(0)
+}
+
+/// An algorithm together with its parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "algorithm", rename_all = "snake_case")]
+pub enum AlgorithmParams {
+    Ola(OverlapAddParams),
+    Sola(OverlapAddParams),
+    PhaseVocoder(PhaseVocoderParams),
+    Granular(GrainParams),
+}
+
+impl AlgorithmParams {
+    /// Build the algorithm, resolving its sizes against `sample_rate`.
+    pub fn stretcher(&self, sample_rate: u32) -> Result<Box<dyn TimeStretcher>, StretchError> {
+        Ok(match self {
+            AlgorithmParams::Ola(params) => Box::new(Ola {
+                frames: params.frames.resolve(sample_rate)?,
+                window_fn: params.window.function(),
+                crossfade: params.crossfade.map(|c| c.resolve(sample_rate)),
+            }),
+            AlgorithmParams::Sola(params) => Box::new(Sola {
+                frames: params.frames.resolve(sample_rate)?,
+                window_fn: params.window.function(),
+                crossfade: params.crossfade.map(|c| c.resolve(sample_rate)),
+            }),
+            AlgorithmParams::PhaseVocoder(params) => Box::new(PhaseVocoder {
+                frames: params.frames.resolve(sample_rate)?,
+                transient_cutoff: params.transient_cutoff,
+                window_fn: params.window.function(),
+            }),
+            AlgorithmParams::Granular(params) => {
+                let granular_params = GranularParams {
+                    grain_size: params.grain_size.to_samples(sample_rate),
+                    density: params.density,
+                    position_jitter: params.position_jitter.to_samples(sample_rate),
+                    pitch_jitter: params.pitch_jitter,
+                    seed: params.seed,
+                };
+                granular_params.validate()?;
+
+                Box::new(Granular { params: granular_params, window_fn: params.window.function() })
+            },
+        })
+    }
+}
+
+impl CrossfadeParams {
+    fn resolve(self, sample_rate: u32) -> Crossfade {
+        Crossfade::new(self.shape, self.overlap.to_samples(sample_rate))
+    }
+}
+
+/// A stretch configuration and algorithm parameters, loadable from TOML or JSON preset files.
+///
+/// ```toml
+/// stretch = 2.0
+/// algorithm = "phase_vocoder"
+/// window = "hann"
+/// window_size = { samples = 4096 }
+/// hop_length = { overlap = 75.0 }
+/// transient_cutoff = 0.25
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    #[serde(flatten)]
+    pub config: StretchConfig,
+    #[serde(flatten)]
+    pub params: AlgorithmParams,
+}
+
+impl Preset {
+    /// The names of the built-in presets.
+    pub const BUILTIN: &[&str] = &[
+        "default",
+        "phase-vocoder-fine",
+        "phase-vocoder-speech",
+        "ola",
+        "sola",
+        "sola-crossfade",
+        "granular",
+        "granular-texture",
+    ];
+
+    /// Get the built-in preset with the given `name`.
+    pub fn builtin(name: &str) -> Option<Preset> {
+        let params = match name {
+            "default" => AlgorithmParams::PhaseVocoder(PhaseVocoderParams {
+                window: WindowFunction::Hann,
+                frames: FrameSpec::new(Length::Samples(4096), Length::Samples(1024)),
+                transient_cutoff: 0.25,
+            }),
+            "phase-vocoder-fine" => AlgorithmParams::PhaseVocoder(PhaseVocoderParams {
+                window: WindowFunction::Hann,
+                frames: FrameSpec::new(Length::Samples(8192), Length::Samples(2048)),
+                transient_cutoff: 0.25,
+            }),
+            "phase-vocoder-speech" => AlgorithmParams::PhaseVocoder(PhaseVocoderParams {
+                window: WindowFunction::Hann,
+                frames: FrameSpec::new(Length::Ms(20.0), Length::Ms(8.0)),
+                transient_cutoff: 0.25,
+            }),
+            "ola" => AlgorithmParams::Ola(OverlapAddParams {
+                window: WindowFunction::Hann,
+                frames: FrameSpec::new(Length::Samples(2048), Length::Samples(512)),
+                crossfade: None,
+            }),
+            "sola" => AlgorithmParams::Sola(OverlapAddParams {
+                window: WindowFunction::Hann,
+                frames: FrameSpec::new(Length::Samples(2048), Length::Samples(512)),
+                crossfade: None,
+            }),
+            "sola-crossfade" => AlgorithmParams::Sola(OverlapAddParams {
+                window: WindowFunction::Hann,
+                frames: FrameSpec::new(Length::Samples(2048), Length::Samples(512)),
+                crossfade: Some(CrossfadeParams {
+                    shape: CrossfadeShape::Adaptive { threshold: 0.5 },
+                    overlap: Length::Ms(10.0),
+                }),
+            }),
+            "granular" => AlgorithmParams::Granular(GrainParams {
+                window: WindowFunction::Hann,
+                grain_size: Length::Ms(50.0),
+                density: 4.0,
+                position_jitter: Length::Samples(0),
+                pitch_jitter: 0.0,
+                seed: 0,
+            }),
+            "granular-texture" => AlgorithmParams::Granular(GrainParams {
+                window: WindowFunction::Hann,
+                grain_size: Length::Ms(100.0),
+                density: 8.0,
+                position_jitter: Length::Ms(20.0),
+                pitch_jitter: 15.0,
+                seed: 0,
+            }),
+            _ => return None,
+        };
+
+        Some(Preset { config: StretchConfig::default(), params })
+    }
+
+    /// Get the built-in preset called `name`, or load the preset file at that path otherwise.
+    pub fn resolve(name: &str) -> Result<Preset, PresetError> {
+        match Preset::builtin(name) {
+            Some(preset) => Ok(preset),
+            None => Preset::load(name),
+        }
+    }
+
+    /// Load the preset file at `path`, choosing the format by its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Preset, PresetError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+
+        match PresetFormat::from_path(path)? {
+            PresetFormat::Toml => Preset::from_toml(&contents),
+            PresetFormat::Json => Preset::from_json(&contents),
+        }
+    }
+
+    /// Save the preset to `path`, choosing the format by its extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PresetError> {
+        let path = path.as_ref();
+
+        let contents = match PresetFormat::from_path(path)? {
+            PresetFormat::Toml => self.to_toml()?,
+            PresetFormat::Json => self.to_json()?,
+        };
+
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    pub fn from_toml(s: &str) -> Result<Preset, PresetError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn from_json(s: &str) -> Result<Preset, PresetError> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, PresetError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, PresetError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Build the preset's algorithm, resolving its sizes against `sample_rate`.
+    pub fn stretcher(&self, sample_rate: u32) -> Result<Box<dyn TimeStretcher>, StretchError> {
+        self.params.stretcher(sample_rate)
+    }
+}
+
+enum PresetFormat {
+    Toml,
+    Json,
+}
+
+impl PresetFormat {
+    fn from_path(path: &Path) -> Result<Self, PresetError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("toml") => Ok(PresetFormat::Toml),
+            Some(e) if e.eq_ignore_ascii_case("json") => Ok(PresetFormat::Json),
+            _ => Err(PresetError::UnknownFormat(path.display().to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PresetError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    TomlRead(#[from] toml::de::Error),
+    #[error(transparent)]
+    TomlWrite(#[from] toml::ser::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("`{0}` is neither a built-in preset nor a .toml or .json preset file")]
+    UnknownFormat(String),
+}
@@ -0,0 +1,92 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+
+use crate::stretcher::StretchError;
+
+/// A flag that can be shared with another thread to cancel a running render.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request that every render observing this token stops at its next frame.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The processing stage a [`Progress`] report refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Stft,
+    PhaseAccumulation,
+    Istft,
+    Ola,
+    Sola,
+    Granular,
+}
+
+/// The number of frames of a stage that have been processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub stage: Stage,
+    /// The channel being processed, which is 0 unless the monitor was given another with
+    /// [`Monitor::with_channel`].
+    pub channel: usize,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Reports the progress of a render and checks for cancellation between frames.
+///
+/// The default monitor does neither.
+#[derive(Clone, Copy, Default)]
+pub struct Monitor<'a> {
+    progress: Option<&'a (dyn Fn(Progress) + Sync)>,
+    cancellation: Option<&'a CancellationToken>,
+    channel: usize,
+}
+
+impl<'a> Monitor<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `progress` after every processed frame.
+    pub fn with_progress(mut self, progress: &'a (dyn Fn(Progress) + Sync)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop with [`StretchError::Cancelled`] once `token` is cancelled.
+    pub fn with_cancellation(mut self, token: &'a CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Report progress as that of `channel`, for renders that process channels separately.
+    pub fn with_channel(mut self, channel: usize) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Record that `done` of the `total` frames of `stage` have been processed, returning
+    /// [`StretchError::Cancelled`] if the render should stop.
+    pub fn frame(&self, stage: Stage, done: usize, total: usize) -> Result<(), StretchError> {
+        if self.cancellation.is_some_and(|token| token.is_cancelled()) {
+            return Err(StretchError::Cancelled);
+        }
+
+        if let Some(progress) = self.progress {
+            progress(Progress { stage, channel: self.channel, done, total });
+        }
+
+        Ok(())
+    }
+}
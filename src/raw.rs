@@ -0,0 +1,162 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    dither::Dither,
+    sample::{AudioSample, ConvertSample, Encoding, Endianness, I24},
+    signal::{self, MultiChannelSignal, Signal, SignalReadError, SignalWriteError, STDIO_PATH},
+};
+
+/// How the samples of a headerless PCM stream are stored, which the caller must know to read it.
+///
+/// 8-bit samples are signed, unlike those of WAV files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFormat {
+    pub encoding: Encoding,
+    pub endianness: Endianness,
+}
+
+impl RawFormat {
+    pub fn new(encoding: Encoding, endianness: Endianness) -> Self {
+        Self { encoding, endianness }
+    }
+}
+
+/// Formats as the encoding and byte order separated by a comma, such as `int16,little`.
+impl fmt::Display for RawFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.encoding, self.endianness)
+    }
+}
+
+impl FromStr for RawFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (encoding, endianness) = s.split_once(',')
+            .ok_or_else(|| format!("expected an encoding and a byte order such as `int16,little`, got `{s}`"))?;
+
+        Ok(Self::new(encoding.trim().parse()?, endianness.trim().parse()?))
+    }
+}
+
+/// Read the raw PCM file at the given `path`, with `channels` interleaved channels at
+/// `sample_rate`, converting to the appropriate type.
+///
+/// A trailing partial frame is dropped. A `path` of `-` reads from standard input.
+pub fn read<T, P>(path: P, format: RawFormat, channels: u16, sample_rate: u32) -> Result<Signal<T>, SignalReadError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    let path = path.as_ref();
+    if path == Path::new(STDIO_PATH) {
+        return read_from(io::stdin().lock(), format, channels, sample_rate);
+    }
+
+    read_from(BufReader::new(File::open(path)?), format, channels, sample_rate)
+}
+
+/// Read a raw PCM stream from `reader` to its end, like [`read`].
+pub fn read_from<T, R>(mut reader: R, format: RawFormat, channels: u16, sample_rate: u32) -> Result<Signal<T>, SignalReadError>
+where
+    R: Read,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    if channels == 0 {
+        return Err(SignalReadError::NoChannels);
+    }
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let channels = channels as usize;
+    let mut samples = decode(&bytes, format);
+    samples.truncate(samples.len() - samples.len() % channels);
+
+    let frames = samples.len() / channels;
+    let samples = MultiChannelSignal::from_shape_vec((frames, channels), samples).expect("whole frames");
+
+    Ok(Signal::new(samples, sample_rate))
+}
+
+/// Write the samples of `signal` to a raw PCM file at the given `path`, interleaved and stored
+/// according to `format`, adding `dither` when they are rounded to integers of a lower
+/// resolution.
+///
+/// The sample rate, layout and metadata of the signal are not stored. A `path` of `-` writes to
+/// standard output.
+pub fn write<T, P>(signal: &Signal<T>, path: P, format: RawFormat, dither: Option<Dither>) -> Result<(), SignalWriteError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+{
+    let path = path.as_ref();
+    if path == Path::new(STDIO_PATH) {
+        return write_to(signal, BufWriter::new(io::stdout().lock()), format, dither).map(|_| ());
+    }
+
+    write_to(signal, BufWriter::new(File::create(path)?), format, dither).map(|_| ())
+}
+
+/// Write the samples of `signal` to `writer`, like [`write`].
+pub fn write_to<T, W>(signal: &Signal<T>, mut writer: W, format: RawFormat, dither: Option<Dither>) -> Result<W, SignalWriteError>
+where
+    W: Write,
+    T: AudioSample,
+{
+    writer.write_all(&encode(signal, format, dither))?;
+    writer.flush()?;
+
+    Ok(writer)
+}
+
+/// Decode the interleaved samples stored in `bytes` according to `format`, ignoring a trailing
+/// partial sample.
+pub(crate) fn decode<T>(bytes: &[u8], format: RawFormat) -> Vec<T>
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    bytes.chunks_exact(format.encoding.bytes_per_sample())
+        .map(|sample| format.encoding.decode(sample, format.endianness))
+        .collect()
+}
+
+/// Encode the samples of `signal`, interleaved, according to `format`.
+pub(crate) fn encode<T: AudioSample>(signal: &Signal<T>, format: RawFormat, dither: Option<Dither>) -> Vec<u8> {
+    let RawFormat { encoding, endianness } = format;
+
+    let mut quantizer = signal::quantizer::<T>(dither, encoding, signal.channels());
+    let mut bytes = Vec::with_capacity(signal.samples().len() * encoding.bytes_per_sample());
+    for &sample in signal.samples() {
+        match &mut quantizer {
+            Some(quantizer) => encoding.encode(quantizer.quantize(sample.convert_sample()), endianness, &mut bytes),
+            None => encoding.encode(sample, endianness, &mut bytes),
+        }
+    }
+
+    bytes
+}
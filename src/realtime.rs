@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use crate::{
+    streaming::StreamingPhaseVocoder,
+    stretcher::{check_ratio, FrameConfig, StretchError},
+    windows::WindowFn,
+};
+
+/// A real-time pitch shifter built on the [`StreamingPhaseVocoder`], which stretches the input by
+/// the pitch ratio and resamples it back to the original duration.
+///
+/// All buffers are allocated by [`prepare`](Self::prepare), after which
+/// [`process`](Self::process) never allocates or locks, so it can run inside an audio callback.
+/// The output is delayed by [`latency`](Self::latency) samples.
+pub struct RealtimePitchShifter {
+    frames: FrameConfig,
+    pitch: f32,
+    transient_cutoff: f32,
+    window_fn: WindowFn,
+
+    vocoder: Option<StreamingPhaseVocoder>,
+    max_block_size: usize,
+    sample_rate: u32,
+
+    /// Stretched samples waiting to be resampled, starting at the absolute index
+    /// `stretched_offset`.
+    stretched: VecDeque<f32>,
+    stretched_offset: usize,
+    /// The number of output samples resampled so far.
+    resampled: usize,
+    output: VecDeque<f32>,
+    block: Vec<f32>,
+}
+
+impl RealtimePitchShifter {
+    pub fn new(
+        pitch: f32,
+        frames: FrameConfig,
+        transient_cutoff: f32,
+        window_fn: WindowFn,
+    ) -> Result<Self, StretchError> {
+        check_ratio("pitch", pitch)?;
+
+        Ok(Self {
+            frames,
+            pitch,
+            transient_cutoff,
+            window_fn,
+            vocoder: None,
+            max_block_size: 0,
+            sample_rate: 0,
+            stretched: VecDeque::new(),
+            stretched_offset: 0,
+            resampled: 0,
+            output: VecDeque::new(),
+            block: Vec::new(),
+        })
+    }
+
+    /// Allocate every buffer needed to process blocks of up to `max_block_size` samples at
+    /// `sample_rate`, and reset the processing state.
+    pub fn prepare(&mut self, max_block_size: usize, sample_rate: u32) -> Result<(), StretchError> {
+        let mut vocoder = StreamingPhaseVocoder::new(self.pitch, self.frames, self.transient_cutoff, self.window_fn)?;
+        vocoder.reserve(max_block_size);
+
+        let window_size = self.frames.window_size();
+        let hop_length = self.frames.hop_length();
+        let stretched_block = (max_block_size as f32 * self.pitch).ceil() as usize + window_size + 2 * hop_length;
+
+        self.vocoder = Some(vocoder);
+        self.max_block_size = max_block_size;
+        self.sample_rate = sample_rate;
+
+        self.stretched = VecDeque::with_capacity(2 * stretched_block);
+        self.output = VecDeque::with_capacity(self.latency() + 2 * max_block_size);
+        self.block = vec![0.0; stretched_block];
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Clear all buffered audio, keeping every allocation.
+    pub fn reset(&mut self) {
+        if let Some(vocoder) = &mut self.vocoder {
+            vocoder.reset();
+        }
+
+        self.stretched.clear();
+        self.stretched_offset = 0;
+        self.resampled = 0;
+
+        // Prime the output with silence so that it never runs dry while the vocoder fills up.
+        self.output.clear();
+        self.output.resize(self.latency(), 0.0);
+    }
+
+    /// Pitch shift one block of `input` into `output`.
+    ///
+    /// If [`prepare`](Self::prepare) has not been called, the blocks differ in length or they are
+    /// longer than the prepared maximum block size, `output` is filled with silence and an error
+    /// is returned, leaving the processing state untouched.
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) -> Result<(), StretchError> {
+        let checked = match &mut self.vocoder {
+            None => Err(StretchError::NotPrepared),
+            Some(_) if input.len() != output.len() => Err(StretchError::BlockMismatch { input: input.len(), output: output.len() }),
+            Some(_) if input.len() > self.max_block_size => {
+                Err(StretchError::BlockTooLong { len: input.len(), max_block_size: self.max_block_size })
+            },
+            Some(vocoder) => Ok(vocoder),
+        };
+        let vocoder = match checked {
+            Ok(vocoder) => vocoder,
+            Err(e) => {
+                output.fill(0.0);
+                return Err(e);
+            },
+        };
+
+        // The vocoder is never finished, so pushing cannot fail.
+        vocoder.push(input)?;
+        loop {
+            let len = vocoder.pull(&mut self.block);
+            if len == 0 {
+                break;
+            }
+
+            self.stretched.extend(&self.block[..len]);
+        }
+
+        // Resample the stretched signal by the pitch ratio, back to the input's duration.
+        loop {
+            let x = self.resampled as f64 * self.pitch as f64;
+            let i = x.floor() as usize;
+            let d = (x - x.floor()) as f32;
+
+            let (Some(&s0), Some(&s1)) = (
+                self.stretched.get(i - self.stretched_offset),
+                self.stretched.get(i + 1 - self.stretched_offset),
+            ) else {
+                break;
+            };
+
+            self.output.push_back(s0 + (s1 - s0) * d);
+            self.resampled += 1;
+
+            let discard = (i - self.stretched_offset).min(self.stretched.len());
+            self.stretched.drain(..discard);
+            self.stretched_offset += discard;
+        }
+
+        for out in output.iter_mut() {
+            *out = self.output.pop_front().unwrap_or(0.0);
+        }
+
+        Ok(())
+    }
+
+    /// The number of samples the vocoder must read before it can analyze its first frame.
+    pub fn input_latency(&self) -> usize {
+        self.frames.window_size()
+    }
+
+    /// The number of samples of additional buffering needed to deliver the output in blocks.
+    ///
+    /// Output sample `r` is resampled from stretched samples up to `⌊r · pitch⌋ + 1`, which the
+    /// vocoder completes with the synthesis frame after it. That frame reads the analysis frame
+    /// of input up to less than `r + 1 / pitch + hop_length + window_size`, so that after every
+    /// block at most `hop_length + ⌈1 / pitch⌉` samples beyond the input latency are missing.
+    pub fn output_latency(&self) -> usize {
+        self.frames.hop_length() + (1.0 / self.pitch as f64).ceil() as usize
+    }
+
+    /// The total delay of the output relative to the input, in samples.
+    pub fn latency(&self) -> usize {
+        self.input_latency() + self.output_latency()
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// The sample rate given to [`prepare`](Self::prepare).
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
@@ -0,0 +1,26 @@
+use crate::{sample::{AudioSample, ConvertSample}, signal::TimeDomainSignal};
+
+/// Resample the given signal to `len` samples using linear interpolation.
+pub fn resample_linear<T>(signal: &TimeDomainSignal<T>, len: usize) -> TimeDomainSignal<T>
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    if signal.is_empty() {
+        return TimeDomainSignal::from_elem(len, T::zero());
+    }
+
+    let ratio = signal.len() as f32 / len as f32;
+    let last = signal.len() - 1;
+
+    (0..len).map(|i| {
+        let x = i as f32 * ratio;
+        let i0 = (x.floor() as usize).min(last);
+        let i1 = (i0 + 1).min(last);
+        let d = x - x.floor();
+
+        let s0: f32 = signal[i0].convert_sample();
+        let s1: f32 = signal[i1].convert_sample();
+        (s0 + (s1 - s0) * d).convert_sample()
+    }).collect()
+}
@@ -1,4 +1,4 @@
-use std::alloc::Layout;
+use std::{alloc::Layout, fmt, io, str::FromStr};
 
 use num_traits::NumAssign;
 
@@ -8,9 +8,12 @@ pub trait AudioSample:
     + num_traits::Bounded
     + std::ops::Neg<Output = Self>
     + PartialOrd
+    + ConvertSample<i8>
     + ConvertSample<i16>
+    + ConvertSample<I24>
     + ConvertSample<i32>
     + ConvertSample<f32>
+    + ConvertSample<f64>
     + NumAssign
     + Sync
     + Send
@@ -20,6 +23,16 @@ pub trait AudioSample:
     fn bits_per_sample() -> u16;
 }
 
+impl AudioSample for i8 {
+    fn sample_format() -> SampleFormat {
+        SampleFormat::Int
+    }
+
+    fn bits_per_sample() -> u16 {
+        8
+    }
+}
+
 impl AudioSample for i16 {
     fn sample_format() -> SampleFormat {
         SampleFormat::Int
@@ -36,7 +49,17 @@ impl AudioSample for i32 {
     }
 
     fn bits_per_sample() -> u16 {
-        16
+        32
+    }
+}
+
+impl AudioSample for I24 {
+    fn sample_format() -> SampleFormat {
+        SampleFormat::Int
+    }
+
+    fn bits_per_sample() -> u16 {
+        24
     }
 }
 
@@ -50,12 +73,340 @@ impl AudioSample for f32 {
     }
 }
 
+impl AudioSample for f64 {
+    fn sample_format() -> SampleFormat {
+        SampleFormat::Float
+    }
+
+    fn bits_per_sample() -> u16 {
+        64
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SampleFormat {
     Int,
     Float,
 }
 
+/// How samples are stored in a file, independently of the type they are processed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Int8,
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+    Float64,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 6] = [
+        Encoding::Int8,
+        Encoding::Int16,
+        Encoding::Int24,
+        Encoding::Int32,
+        Encoding::Float32,
+        Encoding::Float64,
+    ];
+
+    /// The encoding that stores samples of type `T` as they are.
+    pub fn of<T: AudioSample>() -> Self {
+        Self::from_spec(T::sample_format(), T::bits_per_sample()).expect("every sample type has an encoding")
+    }
+
+    /// The encoding with the given format and width, if there is one.
+    pub fn from_spec(sample_format: SampleFormat, bits_per_sample: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.sample_format() == sample_format && e.bits_per_sample() == bits_per_sample)
+    }
+
+    /// The narrowest encoding with the given format that holds samples of `bits_per_sample` bits,
+    /// such as 24-bit integers for 20-bit samples.
+    pub fn holding(sample_format: SampleFormat, bits_per_sample: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.sample_format() == sample_format && e.bits_per_sample() >= bits_per_sample)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Int8 => "int8",
+            Encoding::Int16 => "int16",
+            Encoding::Int24 => "int24",
+            Encoding::Int32 => "int32",
+            Encoding::Float32 => "float32",
+            Encoding::Float64 => "float64",
+        }
+    }
+
+    pub fn sample_format(self) -> SampleFormat {
+        match self {
+            Encoding::Float32 | Encoding::Float64 => SampleFormat::Float,
+            _ => SampleFormat::Int,
+        }
+    }
+
+    pub fn bits_per_sample(self) -> u16 {
+        match self {
+            Encoding::Int8 => 8,
+            Encoding::Int16 => 16,
+            Encoding::Int24 => 24,
+            Encoding::Int32 | Encoding::Float32 => 32,
+            Encoding::Float64 => 64,
+        }
+    }
+
+    pub fn bytes_per_sample(self) -> usize {
+        self.bits_per_sample() as usize / 8
+    }
+
+    /// Convert `sample` to this encoding and append it to `bytes` in the given byte order, with
+    /// 8-bit samples signed.
+    pub fn encode<T: AudioSample>(self, sample: T, endianness: Endianness, bytes: &mut Vec<u8>) {
+        macro_rules! encode {
+            ($t:ty) => {{
+                let sample = ConvertSample::<$t>::convert_sample(sample);
+                match endianness {
+                    Endianness::Little => bytes.extend_from_slice(&sample.to_le_bytes()),
+                    Endianness::Big => bytes.extend_from_slice(&sample.to_be_bytes()),
+                }
+            }};
+        }
+
+        match self {
+            Encoding::Int8 => encode!(i8),
+            Encoding::Int16 => encode!(i16),
+            Encoding::Int24 => encode!(I24),
+            Encoding::Int32 => encode!(i32),
+            Encoding::Float32 => encode!(f32),
+            Encoding::Float64 => encode!(f64),
+        }
+    }
+
+    /// Convert the sample stored with this encoding at the start of `bytes` in the given byte
+    /// order, with 8-bit samples signed.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` is shorter than a sample.
+    pub fn decode<T>(self, bytes: &[u8], endianness: Endianness) -> T
+    where
+        T: AudioSample,
+        f32: ConvertSample<T>,
+        f64: ConvertSample<T>,
+        i8: ConvertSample<T>,
+        i16: ConvertSample<T>,
+        I24: ConvertSample<T>,
+        i32: ConvertSample<T>,
+    {
+        macro_rules! decode {
+            ($t:ty) => {{
+                let bytes = bytes[..self.bytes_per_sample()].try_into().unwrap();
+                match endianness {
+                    Endianness::Little => <$t>::from_le_bytes(bytes).convert_sample(),
+                    Endianness::Big => <$t>::from_be_bytes(bytes).convert_sample(),
+                }
+            }};
+        }
+
+        match self {
+            Encoding::Int8 => decode!(i8),
+            Encoding::Int16 => decode!(i16),
+            Encoding::Int24 => decode!(I24),
+            Encoding::Int32 => decode!(i32),
+            Encoding::Float32 => decode!(f32),
+            Encoding::Float64 => decode!(f64),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::ALL.into_iter()
+            .find(|e| e.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown sample encoding `{s}`"))
+    }
+}
+
+/// The byte order of samples wider than a byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    /// Least significant byte first, as in WAV files.
+    #[default]
+    Little,
+    /// Most significant byte first, as in AIFF files.
+    Big,
+}
+
+impl Endianness {
+    pub const ALL: [Endianness; 2] = [Endianness::Little, Endianness::Big];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Endianness::Little => "little",
+            Endianness::Big => "big",
+        }
+    }
+}
+
+impl fmt::Display for Endianness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Endianness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Endianness::ALL.into_iter()
+            .find(|e| e.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown byte order `{s}`"))
+    }
+}
+
+/// A signed 24-bit integer sample, stored in three bytes in files.
+///
+/// Arithmetic saturates at the bounds of the 24-bit range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct I24(i32);
+
+impl I24 {
+    pub const MIN: I24 = I24(-(1 << 23));
+    pub const MAX: I24 = I24((1 << 23) - 1);
+
+    /// The sample with the given `value`, or `None` if it does not fit in 24 bits.
+    pub fn new(value: i32) -> Option<Self> {
+        (Self::MIN.0..=Self::MAX.0).contains(&value).then_some(I24(value))
+    }
+
+    pub fn get(self) -> i32 {
+        self.0
+    }
+
+    pub fn from_le_bytes(bytes: [u8; 3]) -> Self {
+        I24(i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8)
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 3]) -> Self {
+        I24(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], 0]) >> 8)
+    }
+
+    pub fn to_le_bytes(self) -> [u8; 3] {
+        let [b0, b1, b2, _] = self.0.to_le_bytes();
+        [b0, b1, b2]
+    }
+
+    pub fn to_be_bytes(self) -> [u8; 3] {
+        let [_, b0, b1, b2] = self.0.to_be_bytes();
+        [b0, b1, b2]
+    }
+
+    fn saturating(value: i64) -> Self {
+        I24(value.clamp(Self::MIN.0 as i64, Self::MAX.0 as i64) as i32)
+    }
+}
+
+impl From<I24> for i32 {
+    fn from(sample: I24) -> i32 {
+        sample.0
+    }
+}
+
+macro_rules! i24_op {
+    ($op:ident, $op_fn:ident, $assign:ident, $assign_fn:ident) => {
+        impl std::ops::$op for I24 {
+            type Output = I24;
+
+            #[inline(always)]
+            fn $op_fn(self, rhs: I24) -> I24 {
+                I24::saturating(std::ops::$op::$op_fn(self.0 as i64, rhs.0 as i64))
+            }
+        }
+
+        impl std::ops::$assign for I24 {
+            #[inline(always)]
+            fn $assign_fn(&mut self, rhs: I24) {
+                *self = std::ops::$op::$op_fn(*self, rhs);
+            }
+        }
+    };
+}
+
+i24_op!(Add, add, AddAssign, add_assign);
+i24_op!(Sub, sub, SubAssign, sub_assign);
+i24_op!(Mul, mul, MulAssign, mul_assign);
+i24_op!(Div, div, DivAssign, div_assign);
+i24_op!(Rem, rem, RemAssign, rem_assign);
+
+impl std::ops::Neg for I24 {
+    type Output = I24;
+
+    #[inline(always)]
+    fn neg(self) -> I24 {
+        I24::saturating(-(self.0 as i64))
+    }
+}
+
+impl num_traits::Zero for I24 {
+    fn zero() -> Self {
+        I24(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl num_traits::One for I24 {
+    fn one() -> Self {
+        I24(1)
+    }
+}
+
+impl num_traits::Bounded for I24 {
+    fn min_value() -> Self {
+        I24::MIN
+    }
+
+    fn max_value() -> Self {
+        I24::MAX
+    }
+}
+
+impl num_traits::Num for I24 {
+    type FromStrRadixErr = std::num::ParseIntError;
+
+    /// Parse a sample, saturating values outside the 24-bit range.
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        i64::from_str_radix(str, radix).map(I24::saturating)
+    }
+}
+
+impl hound::Sample for I24 {
+    fn write<W: io::Write>(self, writer: &mut W, bits: u16) -> hound::Result<()> {
+        self.0.write(writer, bits)
+    }
+
+    fn write_padded<W: io::Write>(self, writer: &mut W, bits: u16, byte_width: u16) -> hound::Result<()> {
+        self.0.write_padded(writer, bits, byte_width)
+    }
+
+    fn read<R: io::Read>(reader: &mut R, format: hound::SampleFormat, bytes: u16, bits: u16) -> hound::Result<Self> {
+        I24::new(i32::read(reader, format, bytes, bits)?).ok_or(hound::Error::TooWide)
+    }
+
+    fn as_i16(self) -> i16 {
+        (self.0 >> 8) as i16
+    }
+}
+
 pub trait ConvertSample<T: AudioSample> {
     fn convert_sample(self) -> T
     where
@@ -95,6 +446,55 @@ where
     }
 }
 
+impl ConvertSample<i8> for i8 {
+    #[inline(always)]
+    fn convert_sample(self) -> i8 {
+        self
+    }
+}
+
+impl ConvertSample<i16> for i8 {
+    #[inline(always)]
+    fn convert_sample(self) -> i16 {
+        (self as i16) << 8
+    }
+}
+
+impl ConvertSample<I24> for i8 {
+    #[inline(always)]
+    fn convert_sample(self) -> I24 {
+        I24((self as i32) << 16)
+    }
+}
+
+impl ConvertSample<i32> for i8 {
+    #[inline(always)]
+    fn convert_sample(self) -> i32 {
+        (self as i32) << 24
+    }
+}
+
+impl ConvertSample<f32> for i8 {
+    #[inline(always)]
+    fn convert_sample(self) -> f32 {
+        ((self as f32) / (i8::MAX as f32)).clamp(-1.0, 1.0)
+    }
+}
+
+impl ConvertSample<f64> for i8 {
+    #[inline(always)]
+    fn convert_sample(self) -> f64 {
+        ((self as f64) / (i8::MAX as f64)).clamp(-1.0, 1.0)
+    }
+}
+
+impl ConvertSample<i8> for i16 {
+    #[inline(always)]
+    fn convert_sample(self) -> i8 {
+        (self >> 8) as i8
+    }
+}
+
 impl ConvertSample<i16> for i16 {
     #[inline(always)]
     fn convert_sample(self) -> i16 {
@@ -102,6 +502,13 @@ impl ConvertSample<i16> for i16 {
     }
 }
 
+impl ConvertSample<I24> for i16 {
+    #[inline(always)]
+    fn convert_sample(self) -> I24 {
+        I24((self as i32) << 8)
+    }
+}
+
 impl ConvertSample<i32> for i16 {
     #[inline(always)]
     fn convert_sample(self) -> i32 {
@@ -116,6 +523,62 @@ impl ConvertSample<f32> for i16 {
     }
 }
 
+impl ConvertSample<f64> for i16 {
+    #[inline(always)]
+    fn convert_sample(self) -> f64 {
+        ((self as f64) / (i16::MAX as f64)).clamp(-1.0, 1.0)
+    }
+}
+
+impl ConvertSample<i8> for I24 {
+    #[inline(always)]
+    fn convert_sample(self) -> i8 {
+        (self.0 >> 16) as i8
+    }
+}
+
+impl ConvertSample<i16> for I24 {
+    #[inline(always)]
+    fn convert_sample(self) -> i16 {
+        (self.0 >> 8) as i16
+    }
+}
+
+impl ConvertSample<I24> for I24 {
+    #[inline(always)]
+    fn convert_sample(self) -> I24 {
+        self
+    }
+}
+
+impl ConvertSample<i32> for I24 {
+    #[inline(always)]
+    fn convert_sample(self) -> i32 {
+        self.0 << 8
+    }
+}
+
+impl ConvertSample<f32> for I24 {
+    #[inline(always)]
+    fn convert_sample(self) -> f32 {
+        ((self.0 as f32) / (I24::MAX.0 as f32)).clamp(-1.0, 1.0)
+    }
+}
+
+impl ConvertSample<f64> for I24 {
+    #[inline(always)]
+    fn convert_sample(self) -> f64 {
+        ((self.0 as f64) / (I24::MAX.0 as f64)).clamp(-1.0, 1.0)
+    }
+}
+
+impl ConvertSample<i8> for i32 {
+    #[inline(always)]
+    fn convert_sample(self) -> i8 {
+        (self >> 24) as i8
+    }
+}
+
 impl ConvertSample<i16> for i32 {
     #[inline(always)]
     fn convert_sample(self) -> i16 {
@@ -123,6 +586,13 @@ impl ConvertSample<i16> for i32 {
     }
 }
 
+impl ConvertSample<I24> for i32 {
+    #[inline(always)]
+    fn convert_sample(self) -> I24 {
+        I24(self >> 8)
+    }
+}
+
 impl ConvertSample<i32> for i32 {
     #[inline(always)]
     fn convert_sample(self) -> i32 {
@@ -137,6 +607,20 @@ impl ConvertSample<f32> for i32 {
     }
 }
 
+impl ConvertSample<f64> for i32 {
+    #[inline(always)]
+    fn convert_sample(self) -> f64 {
+        ((self as f64) / (i32::MAX as f64)).clamp(-1.0, 1.0)
+    }
+}
+
+impl ConvertSample<i8> for f32 {
+    #[inline(always)]
+    fn convert_sample(self) -> i8 {
+        ((self * (i8::MAX as f32)).clamp(i8::MIN as f32, i8::MAX as f32)).round() as i8
+    }
+}
+
 impl ConvertSample<i16> for f32 {
     #[inline(always)]
     fn convert_sample(self) -> i16 {
@@ -144,6 +628,13 @@ impl ConvertSample<i16> for f32 {
     }
 }
 
+impl ConvertSample<I24> for f32 {
+    #[inline(always)]
+    fn convert_sample(self) -> I24 {
+        I24::saturating((self * (I24::MAX.0 as f32)).round() as i64)
+    }
+}
+
 impl ConvertSample<i32> for f32 {
     #[inline(always)]
     fn convert_sample(self) -> i32 {
@@ -157,3 +648,52 @@ impl ConvertSample<f32> for f32 {
         self
     }
 }
+
+impl ConvertSample<f64> for f32 {
+    #[inline(always)]
+    fn convert_sample(self) -> f64 {
+        self as f64
+    }
+}
+
+impl ConvertSample<i8> for f64 {
+    #[inline(always)]
+    fn convert_sample(self) -> i8 {
+        ((self * (i8::MAX as f64)).clamp(i8::MIN as f64, i8::MAX as f64)).round() as i8
+    }
+}
+
+impl ConvertSample<i16> for f64 {
+    #[inline(always)]
+    fn convert_sample(self) -> i16 {
+        ((self * (i16::MAX as f64)).clamp(i16::MIN as f64, i16::MAX as f64)).round() as i16
+    }
+}
+
+impl ConvertSample<I24> for f64 {
+    #[inline(always)]
+    fn convert_sample(self) -> I24 {
+        I24::saturating((self * (I24::MAX.0 as f64)).round() as i64)
+    }
+}
+
+impl ConvertSample<i32> for f64 {
+    #[inline(always)]
+    fn convert_sample(self) -> i32 {
+        ((self * (i32::MAX as f64)).clamp(i32::MIN as f64, i32::MAX as f64)).round() as i32
+    }
+}
+
+impl ConvertSample<f32> for f64 {
+    #[inline(always)]
+    fn convert_sample(self) -> f32 {
+        self as f32
+    }
+}
+
+impl ConvertSample<f64> for f64 {
+    #[inline(always)]
+    fn convert_sample(self) -> f64 {
+        self
+    }
+}
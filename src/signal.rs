@@ -1,96 +1,1215 @@
-use ndarray::{Array1, Array2};
+use std::{
+    fs::File,
+    fmt,
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+    str::FromStr,
+};
+
+use claxon::FlacReader;
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
 use num_complex::Complex;
 use thiserror::Error;
 
-use crate::sample::{AudioSample, ConvertSample, SampleFormat};
+use crate::{
+    compressed::CompressedStream,
+    aiff::{self, AiffHeader},
+    dither::{Dither, Quantizer},
+    downmix::{Downmix, DownmixError},
+    flac,
+    raw::{self, RawFormat},
+    sample::{AudioSample, ConvertSample, Encoding, Endianness, SampleFormat, I24},
+};
 
 /// A single-channel audio signal stored in the time domain.
-pub type TimeDomainSignal<T: AudioSample> = Array1<T>;
+pub type TimeDomainSignal<T> = Array1<T>;
+
+/// A multichannel audio signal stored in the time domain, with one row per frame and one column
+/// per channel.
+pub type MultiChannelSignal<T> = Array2<T>;
 
 /// A single-channel audio signal stored in the frequency domain.
-pub type FrequencyDomainSignal<T: AudioSample> = Array1<Complex<T>>;
+pub type FrequencyDomainSignal<T> = Array1<Complex<T>>;
+
+pub type SpectrumSignal<T> = Array2<Complex<T>>;
+
+/// The path that stands for standard input when reading and standard output when writing.
+pub const STDIO_PATH: &str = "-";
+
+/// Audio samples together with their sample rate, the speaker layout of their channels and
+/// descriptive metadata, which are carried through reading, processing and writing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal<T> {
+    samples: MultiChannelSignal<T>,
+    sample_rate: u32,
+    layout: ChannelLayout,
+    metadata: Metadata,
+}
+
+impl<T> Signal<T> {
+    /// A signal with one channel per column of `samples`, without speaker positions or metadata.
+    pub fn new(samples: MultiChannelSignal<T>, sample_rate: u32) -> Self {
+        let layout = ChannelLayout::new(samples.ncols());
+        Self { samples, sample_rate, layout, metadata: Metadata::default() }
+    }
+
+    /// A single-channel signal.
+    pub fn mono(samples: TimeDomainSignal<T>, sample_rate: u32) -> Self {
+        Self::new(samples.insert_axis(Axis(1)), sample_rate)
+    }
+
+    /// Set the speaker layout, which must have as many channels as the signal.
+    pub fn with_layout(mut self, layout: ChannelLayout) -> Result<Self, SignalError> {
+        if layout.channels() != self.channels() {
+            return Err(SignalError::LayoutMismatch { layout: layout.channels(), channels: self.channels() });
+        }
+
+        self.layout = layout;
+        Ok(self)
+    }
+
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Replace the samples, keeping the sample rate, layout and metadata, which requires the
+    /// new samples to have as many channels.
+    pub fn with_samples<U>(self, samples: MultiChannelSignal<U>) -> Result<Signal<U>, SignalError> {
+        if samples.ncols() != self.channels() {
+            return Err(SignalError::LayoutMismatch { layout: self.channels(), channels: samples.ncols() });
+        }
+
+        Ok(Signal { samples, sample_rate: self.sample_rate, layout: self.layout, metadata: self.metadata })
+    }
+
+    /// The samples, with one row per frame and one column per channel.
+    pub fn samples(&self) -> &MultiChannelSignal<T> {
+        &self.samples
+    }
+
+    pub fn into_samples(self) -> MultiChannelSignal<T> {
+        self.samples
+    }
+
+    /// The samples of the given `channel`.
+    ///
+    /// # Panics
+    ///
+    /// If the signal has no such channel.
+    pub fn channel(&self, channel: usize) -> ArrayView1<'_, T> {
+        self.samples.column(channel)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    pub fn channels(&self) -> usize {
+        self.samples.ncols()
+    }
+
+    /// The number of samples per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.nrows()
+    }
+
+    /// The duration of the signal, in seconds.
+    pub fn duration(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate as f64
+    }
+}
+
+/// The number of channels of a signal and, if known, the speakers they are meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelLayout {
+    channels: usize,
+    mask: Option<u32>,
+}
+
+impl ChannelLayout {
+    /// `channels` channels without speaker positions.
+    pub fn new(channels: usize) -> Self {
+        Self { channels, mask: None }
+    }
+
+    /// One channel for every speaker in `mask`, a WAVE_FORMAT_EXTENSIBLE channel mask built from
+    /// the [`speaker`](crate::downmix::speaker) positions, in the order of their bits.
+    pub fn with_speakers(mask: u32) -> Self {
+        Self { channels: mask.count_ones() as usize, mask: Some(mask) }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// The speaker positions as a channel mask, if known.
+    pub fn mask(&self) -> Option<u32> {
+        self.mask
+    }
+
+    /// The layout of a file with `channels` channels and the given channel mask, which is ignored
+    /// if it names a different number of speakers.
+    fn from_header(channels: usize, mask: Option<u32>) -> Self {
+        match mask {
+            Some(mask) if mask.count_ones() as usize == channels => Self::with_speakers(mask),
+            _ => Self::new(channels),
+        }
+    }
+}
+
+/// Descriptive tags of a signal, stored in the `LIST` `INFO` chunk of WAV files and the Vorbis
+/// comments of FLAC files.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub date: Option<String>,
+    pub genre: Option<String>,
+    pub comment: Option<String>,
+    /// The software that created the file.
+    pub software: Option<String>,
+}
+
+impl Metadata {
+    /// The tags with their `INFO` chunk identifiers.
+    fn tags(&self) -> [(&'static [u8; 4], &Option<String>); 7] {
+        [
+            (b"INAM", &self.title),
+            (b"IART", &self.artist),
+            (b"IPRD", &self.album),
+            (b"ICRD", &self.date),
+            (b"IGNR", &self.genre),
+            (b"ICMT", &self.comment),
+            (b"ISFT", &self.software),
+        ]
+    }
+
+    fn tag_mut(&mut self, id: &[u8]) -> Option<&mut Option<String>> {
+        match id {
+            b"INAM" => Some(&mut self.title),
+            b"IART" => Some(&mut self.artist),
+            b"IPRD" => Some(&mut self.album),
+            b"ICRD" => Some(&mut self.date),
+            b"IGNR" => Some(&mut self.genre),
+            b"ICMT" => Some(&mut self.comment),
+            b"ISFT" => Some(&mut self.software),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags().iter().all(|(_, value)| value.is_none())
+    }
+
+    /// Parse the subchunks of a `LIST` `INFO` chunk, skipping unknown tags.
+    fn parse_info(&mut self, mut chunk: &[u8]) {
+        while chunk.len() >= 8 {
+            let len = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as usize;
+            let Some(value) = chunk.get(8..8 + len) else { break };
+
+            if let Some(tag) = self.tag_mut(&chunk[0..4]) {
+                let value = value.split(|&b| b == 0).next().unwrap_or_default();
+                *tag = Some(String::from_utf8_lossy(value).into_owned());
+            }
+
+            chunk = chunk.get(8 + len + len % 2..).unwrap_or_default();
+        }
+    }
+
+    /// Encode the tags as a `LIST` `INFO` chunk, or nothing if there are none.
+    fn info_chunk(&self) -> Vec<u8> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut body = b"INFO".to_vec();
+        for (id, value) in self.tags() {
+            let Some(value) = value else { continue };
+            let len = value.len() + 1;
+
+            body.extend_from_slice(id);
+            body.extend_from_slice(&(len as u32).to_le_bytes());
+            body.extend_from_slice(value.as_bytes());
+            body.resize(body.len() + 1 + len % 2, 0);
+        }
+
+        let mut chunk = b"LIST".to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&body);
+        chunk
+    }
+}
+
+/// The container of an audio file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileFormat {
+    #[default]
+    Wav,
+    /// Lossless compression of integer samples of up to 24 bits.
+    Flac,
+    /// Uncompressed big-endian samples, in an AIFF-C file when they are floating-point.
+    Aiff,
+    /// MPEG-1 or MPEG-2 Audio Layer III, which is only read.
+    Mp3,
+    /// Vorbis in an Ogg container, which is only read.
+    Ogg,
+    /// AAC in an MP4 container, as in `.m4a` files, which is only read.
+    Mp4,
+    /// AAC in an ADTS stream, which is only read.
+    Aac,
+}
 
-pub type SpectrumSignal<T: AudioSample> = Array2<Complex<T>>;
+impl FileFormat {
+    pub const ALL: [FileFormat; 7] = [
+        FileFormat::Wav,
+        FileFormat::Flac,
+        FileFormat::Aiff,
+        FileFormat::Mp3,
+        FileFormat::Ogg,
+        FileFormat::Mp4,
+        FileFormat::Aac,
+    ];
 
-/// Read the WAV file at the given `path`, converting to the appropriate type and adding
-/// multiple channels into a single one if necessary.
-pub fn read_mono<T, P>(path: P) -> Result<(TimeDomainSignal<T>, u32), SignalReadError>
+    pub fn name(self) -> &'static str {
+        match self {
+            FileFormat::Wav => "wav",
+            FileFormat::Flac => "flac",
+            FileFormat::Aiff => "aiff",
+            FileFormat::Mp3 => "mp3",
+            FileFormat::Ogg => "ogg",
+            FileFormat::Mp4 => "mp4",
+            FileFormat::Aac => "aac",
+        }
+    }
+
+    /// The file name extensions of the format.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            FileFormat::Wav => &["wav", "wave"],
+            FileFormat::Flac => &["flac"],
+            FileFormat::Aiff => &["aiff", "aif", "aifc"],
+            FileFormat::Mp3 => &["mp3"],
+            FileFormat::Ogg => &["ogg", "oga"],
+            FileFormat::Mp4 => &["m4a", "mp4"],
+            FileFormat::Aac => &["aac"],
+        }
+    }
+
+    /// Whether signals can be written in this format, rather than only read.
+    pub fn is_writable(self) -> bool {
+        matches!(self, FileFormat::Wav | FileFormat::Flac | FileFormat::Aiff)
+    }
+
+    /// The format named by the extension of `path`, if it has a known one.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL.into_iter().find(|f| f.extensions().iter().any(|e| e.eq_ignore_ascii_case(extension)))
+    }
+
+    /// The format of the file starting with `bytes`, recognized by its signature.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b'R', b'I', b'F', b'F', ..] => Some(FileFormat::Wav),
+            [b'f', b'L', b'a', b'C', ..] => Some(FileFormat::Flac),
+            [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C', ..] => Some(FileFormat::Aiff),
+            [b'O', b'g', b'g', b'S', ..] => Some(FileFormat::Ogg),
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(FileFormat::Mp4),
+            [b'I', b'D', b'3', ..] => Some(FileFormat::Mp3),
+            // Both start with a frame sync, which ADTS follows with a layer of zero.
+            &[0xFF, second, ..] if second & 0xF6 == 0xF0 => Some(FileFormat::Aac),
+            &[0xFF, second, ..] if second & 0xE0 == 0xE0 && second & 0x06 != 0 => Some(FileFormat::Mp3),
+            _ => None,
+        }
+    }
+
+    /// Whether files of this format can store samples with `encoding`.
+    pub fn supports(self, encoding: Encoding) -> bool {
+        match self {
+            FileFormat::Wav | FileFormat::Aiff => true,
+            FileFormat::Flac => matches!(encoding, Encoding::Int8 | Encoding::Int16 | Encoding::Int24),
+            _ => false,
+        }
+    }
+
+    /// `encoding` if the format supports it, or otherwise the one that stores such samples with
+    /// the least loss.
+    pub fn closest_encoding(self, encoding: Encoding) -> Encoding {
+        if self.supports(encoding) { encoding } else { Encoding::Int24 }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FileFormat::ALL.into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown file format `{s}`"))
+    }
+}
+
+/// Read the audio file at the given `path`, in any of the [`FileFormat`]s, converting to the
+/// appropriate type and mixing multiple channels into a single one with the
+/// [default](Downmix::Standard) downmix.
+///
+/// The format is recognized by the signature at the start of the file, or else by the extension
+/// of `path`. A `path` of `-` reads from standard input.
+pub fn read_mono<T, P>(path: P) -> Result<Signal<T>, SignalReadError>
 where
-    P: AsRef<std::path::Path>,
-    T: hound::Sample + AudioSample,
+    P: AsRef<Path>,
+    T: AudioSample,
     f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
     i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
     i32: ConvertSample<T>,
 {
-    let reader = hound::WavReader::open(path)?;
-    
-    let spec = reader.spec();
+    read_mono_with(path, &Downmix::default())
+}
 
-    let same_format = if let hound::SampleFormat::Int = spec.sample_format {
-        T::sample_format() == SampleFormat::Int
-    } else {
-        T::sample_format() == SampleFormat::Float
-    };
+/// [`read_mono`], mixing multiple channels into one with the given `downmix`.
+pub fn read_mono_with<T, P>(path: P, downmix: &Downmix) -> Result<Signal<T>, SignalReadError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    Ok(downmix.apply(&read(path)?)?)
+}
+
+/// Read an audio stream from `reader`, like [`read_mono`].
+///
+/// The stream is read to its end first, so that WAV headers written by streaming encoders, whose
+/// sizes are left as zero or `u32::MAX`, can be corrected against the actual length.
+pub fn read_mono_from<T, R>(reader: R) -> Result<Signal<T>, SignalReadError>
+where
+    R: Read,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    let (signal, _) = decode_channels(open_from(reader)?)?;
+    Ok(Downmix::default().apply(&signal)?)
+}
+
+/// Read the audio file at the given `path` with its channels kept apart, converting to the
+/// appropriate type.
+///
+/// A `path` of `-` reads from standard input.
+pub fn read<T, P>(path: P) -> Result<Signal<T>, SignalReadError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    read_channels(path).map(|(signal, _)| signal)
+}
+
+/// [`read`] the file at the given `path`, together with the information in its header.
+pub fn read_channels<T, P>(path: P) -> Result<(Signal<T>, SignalInfo), SignalReadError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    decode_channels(open(path.as_ref())?)
+}
+
+/// An open audio file.
+enum Source {
+    /// A WAV reader together with the parts of its header that hound does not expose.
+    Wav {
+        reader: hound::WavReader<Box<dyn Read>>,
+        channel_mask: Option<u32>,
+        metadata: Metadata,
+    },
+    Flac(FlacReader<Box<dyn Read>>),
+    /// An AIFF reader positioned at the start of the samples.
+    Aiff {
+        reader: Box<dyn Read>,
+        header: AiffHeader,
+    },
+    Compressed(CompressedStream),
+}
+
+/// Open the file at `path`, or standard input for `-`.
+fn open(path: &Path) -> Result<Source, SignalReadError> {
+    if is_stdio(path) {
+        return open_from(io::stdin().lock());
+    }
+
+    let mut file = BufReader::new(File::open(path)?);
+    let format = FileFormat::detect(file.fill_buf()?)
+        .or_else(|| FileFormat::from_path(path))
+        .unwrap_or_default();
+
+    match format {
+        FileFormat::Wav => {
+            let (channel_mask, metadata) = read_chunks(file);
+            let reader: Box<dyn Read> = Box::new(BufReader::new(File::open(path)?));
+
+            Ok(Source::Wav { reader: hound::WavReader::new(reader)?, channel_mask, metadata })
+        },
+        FileFormat::Flac => Ok(Source::Flac(FlacReader::new(Box::new(file) as Box<dyn Read>)?)),
+        FileFormat::Aiff => {
+            let header = aiff::read_header(&mut file)?;
+            Ok(Source::Aiff { reader: Box::new(file), header })
+        },
+        format => Ok(Source::Compressed(CompressedStream::open(Box::new(File::open(path)?), format)?)),
+    }
+}
+
+/// Read a stream to its end and open it, correcting the sizes a streaming WAV encoder left out.
+fn open_from<R: Read>(mut reader: R) -> Result<Source, SignalReadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    match FileFormat::detect(&bytes).unwrap_or_default() {
+        FileFormat::Wav => {
+            fix_streamed_sizes(&mut bytes);
+
+            let (channel_mask, metadata) = read_chunks(Cursor::new(&bytes[..]));
+            let reader: Box<dyn Read> = Box::new(Cursor::new(bytes));
+
+            Ok(Source::Wav { reader: hound::WavReader::new(reader)?, channel_mask, metadata })
+        },
+        FileFormat::Flac => Ok(Source::Flac(FlacReader::new(Box::new(Cursor::new(bytes)) as Box<dyn Read>)?)),
+        FileFormat::Aiff => {
+            let mut reader = Cursor::new(bytes);
+            let header = aiff::read_header(&mut reader)?;
+            Ok(Source::Aiff { reader: Box::new(reader), header })
+        },
+        format => Ok(Source::Compressed(CompressedStream::open(Box::new(Cursor::new(bytes)), format)?)),
+    }
+}
+
+/// Find the channel mask of a WAVE_FORMAT_EXTENSIBLE format chunk and the tags of a `LIST`
+/// `INFO` chunk, which hound does not expose.
+///
+/// Malformed files, which hound reports on its own, yield whatever was found before the error.
+fn read_chunks<R: Read + Seek>(mut reader: R) -> (Option<u32>, Metadata) {
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+    let mut channel_mask = None;
+    let mut metadata = Metadata::default();
+
+    let mut header = [0; 12];
+    if reader.read_exact(&mut header).is_err() || &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return (channel_mask, metadata);
+    }
+
+    let mut chunk = [0; 8];
+    while reader.read_exact(&mut chunk).is_ok() {
+        let len = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as usize;
+        let padded = (len + len % 2) as i64;
+
+        match &chunk[0..4] {
+            b"fmt " if len >= 24 => {
+                let mut format = vec![0; len];
+                if reader.read_exact(&mut format).is_err() {
+                    break;
+                }
+                if len % 2 == 1 && reader.seek(SeekFrom::Current(1)).is_err() {
+                    break;
+                }
+
+                if u16::from_le_bytes([format[0], format[1]]) == WAVE_FORMAT_EXTENSIBLE {
+                    channel_mask = Some(u32::from_le_bytes(format[20..24].try_into().unwrap()));
+                }
+            },
+            b"LIST" if len >= 4 => {
+                let mut list = vec![0; len];
+                if reader.read_exact(&mut list).is_err() {
+                    break;
+                }
+                if len % 2 == 1 && reader.seek(SeekFrom::Current(1)).is_err() {
+                    break;
+                }
 
-    let samples = if same_format {
-        reader.into_samples::<T>().collect::<Result<Vec<T>, _>>()?
-    } else {
-        match T::sample_format() {
-            SampleFormat::Int => {
-                let samples = reader.into_samples::<f32>().collect::<Result<Vec<f32>, _>>()?;
-                samples.into_iter().map(|s| s.convert_sample()).collect::<Vec<T>>()
+                if &list[0..4] == b"INFO" {
+                    metadata.parse_info(&list[4..]);
+                }
             },
-            SampleFormat::Float => {
-                if spec.bits_per_sample == 16 {
-                    let samples = reader.into_samples::<i16>().collect::<Result<Vec<i16>, _>>()?;
-                    samples.into_iter().map(|s| s.convert_sample()).collect::<Vec<T>>()
-                } else {
-                    let samples = reader.into_samples::<i32>().collect::<Result<Vec<i32>, _>>()?;
-                    samples.into_iter().map(|s| s.convert_sample()).collect::<Vec<T>>()
+            _ => {
+                if reader.seek(SeekFrom::Current(padded)).is_err() {
+                    break;
                 }
             },
         }
+    }
+
+    (channel_mask, metadata)
+}
+
+fn decode_channels<T>(source: Source) -> Result<(Signal<T>, SignalInfo), SignalReadError>
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    let mut info = SignalInfo::from_source(&source);
+    let (mut samples, metadata) = match source {
+        Source::Wav { reader, metadata, .. } => (decode_interleaved(reader)?, metadata),
+        Source::Flac(mut reader) => {
+            let metadata = flac::read_metadata(&reader);
+            let samples = flac::read_interleaved(&mut reader)?.into_iter()
+                .map(|s| int_sample(s, info.bits_per_sample).ok_or(claxon::Error::FormatError("sample exceeds the bit depth")))
+                .collect::<Result<_, _>>()?;
+
+            (samples, metadata)
+        },
+        Source::Aiff { reader, header } => {
+            let len = header.frames as u64 * header.channels as u64 * header.encoding.bytes_per_sample() as u64;
+            let mut bytes = Vec::new();
+            reader.take(len).read_to_end(&mut bytes)?;
+
+            (raw::decode(&bytes, RawFormat::new(header.encoding, header.endianness)), header.metadata)
+        },
+        Source::Compressed(mut stream) => {
+            let samples = stream.read_interleaved()?.into_iter().map(f32::convert_sample).collect();
+            info = stream.info();
+
+            (samples, stream.metadata)
+        },
     };
 
-    let samples_mono = samples.chunks_exact(spec.channels as usize)
-        .map(|c| c.iter().fold(T::zero(), |acc, &s| acc + s))
-        .collect::<Vec<T>>();
+    // A compressed stream reports no channels when neither its codec parameters nor a decoded
+    // packet name any.
+    let channels = info.channels as usize;
+    if channels == 0 {
+        return Err(SignalReadError::NoChannels);
+    }
+    samples.truncate(samples.len() - samples.len() % channels);
+
+    let frames = samples.len() / channels;
+    let samples = MultiChannelSignal::from_shape_vec((frames, channels), samples).expect("whole frames");
+    info.frames = frames as u32;
+
+    let signal = Signal::new(samples, info.sample_rate)
+        .with_layout(ChannelLayout::from_header(channels, info.channel_mask))
+        .expect("layout of the header's channel count")
+        .with_metadata(metadata);
 
-    Ok((
-        samples_mono.into(),
-        spec.sample_rate,
-    ))
+    Ok((signal, info))
 }
 
-pub fn write<T, P>(signal: TimeDomainSignal<T>, sample_rate: u32, path: P) -> Result<(), SignalWriteError>
+fn decode_interleaved<T, R>(reader: hound::WavReader<R>) -> Result<Vec<T>, SignalReadError>
 where
-    P: AsRef<std::path::Path>,
-    T: hound::Sample + AudioSample,
+    R: Read,
+    T: AudioSample,
+    f32: ConvertSample<T>,
+    f64: ConvertSample<T>,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
 {
-    let spec = hound::WavSpec {
-        channels: 1,
-        sample_rate,
-        bits_per_sample: T::bits_per_sample(),
-        sample_format: if let SampleFormat::Int = T::sample_format() { hound::SampleFormat::Int } else { hound::SampleFormat::Float },
-    };
+    let spec = reader.spec();
+
+    // hound reads the header of 64-bit float files, when it is WAVE_FORMAT_EXTENSIBLE, but not
+    // their samples.
+    if let (hound::SampleFormat::Float, 64) = (spec.sample_format, spec.bits_per_sample) {
+        let len = reader.len() as usize;
+        let mut data = Vec::with_capacity(len * 8);
+        reader.into_inner().take(len as u64 * 8).read_to_end(&mut data)?;
+
+        return Ok(data.chunks_exact(8)
+            .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()).convert_sample())
+            .collect());
+    }
+
+    if let hound::SampleFormat::Float = spec.sample_format {
+        return reader.into_samples::<f32>()
+            .map(|s| Ok(s?.convert_sample()))
+            .collect();
+    }
+
+    reader.into_samples::<i32>()
+        .map(|s| Ok(int_sample(s?, spec.bits_per_sample).ok_or(hound::Error::TooWide)?))
+        .collect()
+}
+
+/// Convert an integer sample of `bits` bits, or `None` if it does not fit.
+///
+/// Samples are normalized by the range of the narrowest type that holds them, so that full scale
+/// maps to full scale whatever the bit depth of the file. Depths between those of the types are
+/// padded with zeros at the bottom.
+fn int_sample<T>(sample: i32, bits: u16) -> Option<T>
+where
+    T: AudioSample,
+    i8: ConvertSample<T>,
+    i16: ConvertSample<T>,
+    I24: ConvertSample<T>,
+    i32: ConvertSample<T>,
+{
+    Some(match bits {
+        0..=8 => i8::try_from(sample << (8 - bits)).ok()?.convert_sample(),
+        9..=16 => i16::try_from(sample << (16 - bits)).ok()?.convert_sample(),
+        17..=24 => I24::new(sample << (24 - bits))?.convert_sample(),
+        _ => (sample << (32 - bits.min(32))).convert_sample(),
+    })
+}
+
+/// Replace the RIFF and data chunk sizes of a WAV file held in `bytes` when they are missing
+/// or exceed the actual length, as happens when the file was written to a pipe.
+fn fix_streamed_sizes(bytes: &mut [u8]) {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return;
+    }
+
+    let read_u32 = |bytes: &[u8], pos: usize| u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap());
+    let riff_len = u32::try_from(bytes.len() - 8).unwrap_or(u32::MAX);
+    bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+    let mut block_align = 1;
+    let mut pos = 12;
+
+    while pos + 8 <= bytes.len() {
+        let len = read_u32(bytes, pos + 4) as usize;
+        let body = pos + 8;
+
+        if &bytes[pos..pos + 4] == b"fmt " && body + 14 <= bytes.len() {
+            block_align = u16::from_le_bytes([bytes[body + 12], bytes[body + 13]]).max(1) as usize;
+        } else if &bytes[pos..pos + 4] == b"data" {
+            let available = bytes.len() - body;
+
+            if len == 0 || len > available {
+                let len = u32::try_from(available - available % block_align).unwrap_or(u32::MAX);
+                bytes[pos + 4..pos + 8].copy_from_slice(&len.to_le_bytes());
+            }
+
+            return;
+        }
+
+        pos = body.saturating_add(len + len % 2);
+    }
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO_PATH)
+}
+
+/// The format and length of an audio file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalInfo {
+    pub file_format: FileFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// The width of the samples, which is that of the 32-bit floats they are decoded to for
+    /// lossy formats.
+    pub bits_per_sample: u16,
+    pub sample_format: SampleFormat,
+    /// The number of samples per channel.
+    pub frames: u32,
+    /// The speaker positions of the channels, as a WAVE_FORMAT_EXTENSIBLE channel mask (see
+    /// [`speaker`](crate::downmix::speaker)), if the file has one.
+    pub channel_mask: Option<u32>,
+}
+
+impl SignalInfo {
+    fn from_source(source: &Source) -> Self {
+        match source {
+            Source::Wav { reader, channel_mask, .. } => {
+                let spec = reader.spec();
+
+                SignalInfo {
+                    file_format: FileFormat::Wav,
+                    channels: spec.channels,
+                    sample_rate: spec.sample_rate,
+                    bits_per_sample: spec.bits_per_sample,
+                    sample_format: if let hound::SampleFormat::Int = spec.sample_format { SampleFormat::Int } else { SampleFormat::Float },
+                    frames: reader.duration(),
+                    channel_mask: *channel_mask,
+                }
+            },
+            Source::Flac(reader) => {
+                let info = reader.streaminfo();
+
+                SignalInfo {
+                    file_format: FileFormat::Flac,
+                    channels: info.channels as u16,
+                    sample_rate: info.sample_rate,
+                    bits_per_sample: info.bits_per_sample as u16,
+                    sample_format: SampleFormat::Int,
+                    frames: info.samples.unwrap_or(0) as u32,
+                    channel_mask: flac::read_channel_mask(reader),
+                }
+            },
+            Source::Aiff { header, .. } => SignalInfo {
+                file_format: FileFormat::Aiff,
+                channels: header.channels,
+                sample_rate: header.sample_rate,
+                bits_per_sample: header.bits_per_sample,
+                sample_format: header.encoding.sample_format(),
+                frames: header.frames,
+                channel_mask: None,
+            },
+            Source::Compressed(stream) => stream.info(),
+        }
+    }
+
+    /// The duration of the signal, in seconds.
+    pub fn duration(&self) -> f64 {
+        self.frames as f64 / self.sample_rate as f64
+    }
+}
+
+/// Read the header of the audio file at the given `path`, without reading its samples.
+///
+/// The length of MP3 files without a header giving it is reported as zero.
+///
+/// A `path` of `-` reads from standard input, which is consumed entirely.
+pub fn read_info<P>(path: P) -> Result<SignalInfo, SignalReadError>
+where
+    P: AsRef<Path>,
+{
+    Ok(SignalInfo::from_source(&open(path.as_ref())?))
+}
+
+/// How a signal is stored when it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// The file format, or that named by the extension of the path written to if `None`, and
+    /// otherwise WAV.
+    pub format: Option<FileFormat>,
+    /// The encoding of the samples in the file, or that of the signal's sample type if `None`
+    /// and the format supports it.
+    pub encoding: Option<Encoding>,
+    /// The significant bits of integer samples, such as 12 or 20, when fewer than the encoding
+    /// holds. Only FLAC files record it, rounded up to 8, 12, 16, 20 or 24; other formats store
+    /// every bit of the encoding.
+    pub bits_per_sample: Option<u16>,
+    /// The dither added when samples are rounded to integers of a lower resolution, if any.
+    pub dither: Option<Dither>,
+}
+
+/// Write `signal` to a file at the given `path`, with as many channels as it has, its layout
+/// and metadata in the header and its samples stored as they are.
+///
+/// The file is FLAC if `path` ends in `.flac`, AIFF if it ends in `.aiff`, `.aif` or `.aifc`,
+/// and WAV otherwise. FLAC stores floating-point and 32-bit samples as 24-bit integers. A `path`
+/// of `-` writes WAV to standard output.
+pub fn write<T, P>(signal: &Signal<T>, path: P) -> Result<(), SignalWriteError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+{
+    write_with(signal, path, &WriteOptions::default())
+}
+
+/// [`write`], storing the samples according to `options`.
+pub fn write_with<T, P>(signal: &Signal<T>, path: P, options: &WriteOptions) -> Result<(), SignalWriteError>
+where
+    P: AsRef<Path>,
+    T: AudioSample,
+{
+    let path = path.as_ref();
+    let options = WriteOptions { format: options.format.or_else(|| FileFormat::from_path(path)), ..*options };
 
-    let mut writer = hound::WavWriter::create(path, spec)?;
+    if is_stdio(path) {
+        return write_to_with(signal, BufWriter::new(io::stdout().lock()), &options).map(|_| ());
+    }
+
+    write_to_with(signal, BufWriter::new(File::create(path)?), &options).map(|_| ())
+}
+
+/// Write `signal` as a WAV stream to `writer`, which does not need to be seekable.
+pub fn write_to<T, W>(signal: &Signal<T>, writer: W) -> Result<W, SignalWriteError>
+where
+    W: Write,
+    T: AudioSample,
+{
+    write_to_with(signal, writer, &WriteOptions::default())
+}
+
+/// [`write_to`], storing the samples according to `options`, in the format they ask for.
+pub fn write_to_with<T, W>(signal: &Signal<T>, writer: W, options: &WriteOptions) -> Result<W, SignalWriteError>
+where
+    W: Write,
+    T: AudioSample,
+{
+    match options.format.unwrap_or_default() {
+        FileFormat::Wav => {},
+        FileFormat::Flac => return write_flac(signal, writer, options),
+        FileFormat::Aiff => return write_aiff(signal, writer, options),
+        format => return Err(SignalWriteError::UnsupportedFormat(format)),
+    }
+
+    let mut stream = WavStreamWriter::with_header(
+        writer,
+        signal.layout(),
+        signal.sample_rate(),
+        signal.metadata(),
+        Some(signal.frames()),
+        options,
+    )?;
+
+    stream.write_samples(signal.samples().iter().copied())?;
+    stream.finish()
+}
+
+/// Encode `signal` as a FLAC stream, which is built in memory before it is written.
+fn write_flac<T, W>(signal: &Signal<T>, writer: W, options: &WriteOptions) -> Result<W, SignalWriteError>
+where
+    W: Write,
+    T: AudioSample,
+{
+    let encoding = options.encoding.unwrap_or_else(|| FileFormat::Flac.closest_encoding(Encoding::of::<T>()));
+    if !FileFormat::Flac.supports(encoding) {
+        return Err(SignalWriteError::UnsupportedEncoding { format: FileFormat::Flac, encoding });
+    }
+    if !(1..=flac::MAX_CHANNELS).contains(&signal.channels()) {
+        return Err(SignalWriteError::UnsupportedChannels { format: FileFormat::Flac, channels: signal.channels() });
+    }
+    if signal.sample_rate() > flac::MAX_SAMPLE_RATE {
+        return Err(SignalWriteError::UnsupportedSampleRate { format: FileFormat::Flac, sample_rate: signal.sample_rate() });
+    }
+
+    // Samples narrower than the encoding are rounded to the narrowest width FLAC names that holds
+    // their significant bits.
+    let width = encoding.bits_per_sample();
+    let bits = options.bits_per_sample
+        .and_then(|bits| flac::BITS_PER_SAMPLE.into_iter().find(|&b| b >= bits))
+        .map_or(width, |bits| bits.min(width));
+    let shift = width - bits;
+
+    let lossy = T::sample_format() == SampleFormat::Float || T::bits_per_sample() > bits;
+    let mut quantizer = options.dither.filter(|_| lossy).map(|dither| dither.quantizer(bits, signal.channels()));
+    let samples = signal.samples().iter()
+        .map(|&sample| match &mut quantizer {
+            Some(quantizer) => int_value(encoding, quantizer.quantize(sample.convert_sample())),
+            None => int_value(encoding, sample),
+        })
+        .map(|value| match shift {
+            0 => value,
+            shift => ((value as i64 + (1 << (shift - 1))) >> shift).min((1 << (bits - 1)) - 1) as i32,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(flac::encode(writer, &samples, signal.layout(), signal.sample_rate(), bits, signal.metadata())?)
+}
+
+/// Write `signal` as an AIFF stream, or AIFF-C for floating-point samples.
+fn write_aiff<T, W>(signal: &Signal<T>, mut writer: W, options: &WriteOptions) -> Result<W, SignalWriteError>
+where
+    W: Write,
+    T: AudioSample,
+{
+    let encoding = options.encoding.unwrap_or_else(Encoding::of::<T>);
+    let channels = signal.channels() as u16;
+
+    writer.write_all(&aiff::header(channels, signal.frames(), signal.sample_rate(), encoding, signal.metadata())?)?;
+    let samples = raw::encode(signal, RawFormat::new(encoding, Endianness::Big), options.dither);
+    writer.write_all(&samples)?;
+    if samples.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+
+    writer.flush()?;
+    Ok(writer)
+}
+
+/// The quantizer adding `dither` to samples of type `T` stored with `encoding`, if they lose
+/// resolution, which is when floating-point samples or integers wider than the encoding are
+/// stored as integers.
+pub(crate) fn quantizer<T: AudioSample>(dither: Option<Dither>, encoding: Encoding, channels: usize) -> Option<Quantizer> {
+    let lossy = encoding.sample_format() == SampleFormat::Int
+        && (T::sample_format() == SampleFormat::Float || T::bits_per_sample() > encoding.bits_per_sample());
+
+    dither.filter(|_| lossy).map(|dither| dither.quantizer(encoding.bits_per_sample(), channels))
+}
+
+/// Convert `sample` to the integer encoding `encoding`, as an `i32`.
+fn int_value<T: AudioSample>(encoding: Encoding, sample: T) -> i32 {
+    match encoding {
+        Encoding::Int8 => ConvertSample::<i8>::convert_sample(sample) as i32,
+        Encoding::Int16 => ConvertSample::<i16>::convert_sample(sample) as i32,
+        Encoding::Int24 => ConvertSample::<I24>::convert_sample(sample).get(),
+        _ => ConvertSample::<i32>::convert_sample(sample),
+    }
+}
+
+/// Writes a WAV stream sample by sample to a writer that cannot seek, such as a pipe.
+///
+/// The header is written up front. When the length is not known in advance, its sizes are set
+/// to `u32::MAX`, which readers such as sox and ffmpeg take to mean "until the end of the stream".
+pub struct WavStreamWriter<W: Write, T> {
+    writer: W,
+    encoding: Encoding,
+    quantizer: Option<Quantizer>,
+    buffer: Vec<u8>,
+    len: Option<usize>,
+    written: usize,
+    _sample: PhantomData<T>,
+}
+
+impl<W, T> WavStreamWriter<W, T>
+where
+    W: Write,
+    T: AudioSample,
+{
+    /// Write the header for a stream of `frames` frames of `channels` interleaved samples, or of
+    /// unknown length if `None`, stored as they are.
+    pub fn new(writer: W, channels: u16, sample_rate: u32, frames: Option<usize>) -> Result<Self, SignalWriteError> {
+        Self::with_header(
+            writer,
+            ChannelLayout::new(channels as usize),
+            sample_rate,
+            &Metadata::default(),
+            frames,
+            &WriteOptions::default(),
+        )
+    }
+
+    /// [`new`](WavStreamWriter::new), with the speaker positions of `layout` and the tags of
+    /// `metadata` in the header and the samples stored according to `options`.
+    ///
+    /// Dither is only added when the samples lose resolution, which is when floating-point
+    /// samples or integers wider than the encoding are stored as integers.
+    ///
+    /// Layouts with speaker positions or more than two channels, and 64-bit samples, are written
+    /// as WAVE_FORMAT_EXTENSIBLE.
+    pub fn with_header(
+        mut writer: W,
+        layout: ChannelLayout,
+        sample_rate: u32,
+        metadata: &Metadata,
+        frames: Option<usize>,
+        options: &WriteOptions,
+    ) -> Result<Self, SignalWriteError> {
+        const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+        const SUBFORMAT_GUID: [u8; 14] = [0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71];
+
+        let encoding = options.encoding.unwrap_or_else(Encoding::of::<T>);
+        let channels = layout.channels() as u16;
+        let bits_per_sample = encoding.bits_per_sample();
+        let block_align = channels * (bits_per_sample / 8);
+        let format_tag: u16 = if let SampleFormat::Int = encoding.sample_format() { 1 } else { 3 };
+        let extensible = channels > 2 || layout.mask().is_some() || bits_per_sample > 32;
+
+        let info = metadata.info_chunk();
+        let fmt_len: u32 = if extensible { 40 } else { 16 };
+
+        let data_len = frames
+            .and_then(|frames| u32::try_from(frames * block_align as usize).ok())
+            .unwrap_or(u32::MAX);
+        // A data chunk of odd length is followed by a pad byte, which the RIFF chunk counts.
+        let pad = if data_len == u32::MAX { 0 } else { data_len % 2 };
+        let riff_len = data_len.saturating_add(4 + 8 + fmt_len + info.len() as u32 + 8 + pad);
+
+        let mut header = Vec::with_capacity(68 + info.len());
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&riff_len.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&fmt_len.to_le_bytes());
+        header.extend_from_slice(&if extensible { WAVE_FORMAT_EXTENSIBLE } else { format_tag }.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&bits_per_sample.to_le_bytes());
+        if extensible {
+            header.extend_from_slice(&22u16.to_le_bytes());
+            header.extend_from_slice(&bits_per_sample.to_le_bytes());
+            header.extend_from_slice(&layout.mask().unwrap_or(0).to_le_bytes());
+            header.extend_from_slice(&format_tag.to_le_bytes());
+            header.extend_from_slice(&SUBFORMAT_GUID);
+        }
+        header.extend_from_slice(&info);
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_len.to_le_bytes());
+        writer.write_all(&header)?;
+
+        let quantizer = quantizer::<T>(options.dither, encoding, layout.channels());
 
-    for sample in signal.iter() {
-        writer.write_sample(*sample)?;
+        Ok(Self {
+            writer,
+            encoding,
+            quantizer,
+            buffer: Vec::new(),
+            len: frames.map(|frames| frames * channels as usize),
+            written: 0,
+            _sample: PhantomData,
+        })
     }
 
-    Ok(())
+    /// Write interleaved samples.
+    pub fn write_samples<I>(&mut self, samples: I) -> Result<(), SignalWriteError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        const CHUNK: usize = 4096;
+
+        let mut samples = samples.into_iter().peekable();
+        while samples.peek().is_some() {
+            self.buffer.clear();
+            for sample in samples.by_ref().take(CHUNK) {
+                match &mut self.quantizer {
+                    Some(quantizer) => self.encoding.encode(quantizer.quantize(sample.convert_sample()), Endianness::Little, &mut self.buffer),
+                    None => self.encoding.encode(sample, Endianness::Little, &mut self.buffer),
+                }
+                self.written += 1;
+            }
+
+            // 8-bit WAV samples are unsigned.
+            if self.encoding == Encoding::Int8 {
+                self.buffer.iter_mut().for_each(|b| *b ^= 0x80);
+            }
+
+            self.writer.write_all(&self.buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flush the stream and return the writer, checking that the announced length was written.
+    ///
+    /// A data chunk of announced, odd length gets its pad byte. Streams of unknown length are
+    /// read to their end, where a pad byte would be taken for a sample, so they get none.
+    pub fn finish(mut self) -> Result<W, SignalWriteError> {
+        if let Some(len) = self.len {
+            if len != self.written {
+                return Err(SignalWriteError::LengthMismatch { expected: len, written: self.written });
+            }
+            if (len * self.encoding.bytes_per_sample()) % 2 == 1 {
+                self.writer.write_all(&[0])?;
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum SignalReadError {
     #[error(transparent)]
     Wav(#[from] hound::Error),
+    #[error(transparent)]
+    Flac(#[from] claxon::Error),
+    #[error(transparent)]
+    Decode(#[from] symphonia::core::errors::Error),
+    #[error("unsupported codec: {0}")]
+    UnsupportedCodec(String),
+    #[error("the file has no audio track")]
+    NoAudioTrack,
+    #[error("malformed AIFF file: {0}")]
+    Aiff(&'static str),
+    #[error("a signal needs at least one channel")]
+    NoChannels,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Downmix(#[from] DownmixError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SignalError {
+    #[error("a layout of {layout} channels does not fit a signal with {channels} channels")]
+    LayoutMismatch { layout: usize, channels: usize },
 }
 
 #[derive(Debug, Error)]
 pub enum SignalWriteError {
     #[error(transparent)]
-    Wav(#[from] hound::Error),
+    Io(#[from] io::Error),
+    #[error("the stream announced {expected} samples but {written} were written")]
+    LengthMismatch { expected: usize, written: usize },
+    #[error("{0} files can only be read")]
+    UnsupportedFormat(FileFormat),
+    #[error("{format} files cannot store {encoding} samples")]
+    UnsupportedEncoding { format: FileFormat, encoding: Encoding },
+    #[error("{format} files cannot store a sample rate of {sample_rate} Hz")]
+    UnsupportedSampleRate { format: FileFormat, sample_rate: u32 },
+    #[error("{format} files cannot store {channels} channels")]
+    UnsupportedChannels { format: FileFormat, channels: usize },
+    #[error("{frames} frames are too long for a {format} file")]
+    TooLong { format: FileFormat, frames: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flac_round_trips_every_width() {
+        let samples = [1.0, 0.99999, 0.5, 0.0, -0.5, -1.0];
+        let signal = Signal::mono(Array1::from_vec(samples.to_vec()), 44100);
+
+        for bits in flac::BITS_PER_SAMPLE {
+            let options = WriteOptions { format: Some(FileFormat::Flac), bits_per_sample: Some(bits), ..WriteOptions::default() };
+            let bytes = write_to_with(&signal, Vec::new(), &options).unwrap();
+
+            let mut reader = FlacReader::new(Cursor::new(bytes)).unwrap();
+            assert_eq!(reader.streaminfo().bits_per_sample, bits as u32);
+            let decoded = reader.samples().collect::<Result<Vec<_>, _>>().unwrap();
+
+            // Full scale is clamped to the largest positive value, not wrapped to the negative one.
+            let scale = (1 << (bits - 1)) as f32;
+            assert_eq!(decoded[0], (1 << (bits - 1)) - 1, "{bits} bits");
+            for (&sample, &value) in samples.iter().zip(&decoded) {
+                let expected = (sample * scale).clamp(-scale, scale - 1.0);
+                assert!((value as f32 - expected).abs() <= 1.5, "{bits} bits: {sample} became {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn odd_wav_data_is_padded() {
+        let signal = Signal::mono(Array1::from_vec(vec![0.0f32, 0.5, -0.5]), 8000);
+        let options = WriteOptions { encoding: Some(Encoding::Int8), ..WriteOptions::default() };
+        let bytes = write_to_with(&signal, Vec::new(), &options).unwrap();
+
+        assert_eq!(bytes.len() % 2, 0);
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize, bytes.len() - 8);
+
+        let decoded: Signal<f32> = read_mono_from(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoded.frames(), 3);
+    }
 }
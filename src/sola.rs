@@ -1,6 +1,13 @@
 use ndarray::s;
 
-use crate::{sample::AudioSample, signal::TimeDomainSignal, windows::build_window};
+use crate::{
+    crossfade::{correlation, splice, Crossfade},
+    progress::{Monitor, Stage},
+    sample::{AudioSample, ConvertSample},
+    signal::TimeDomainSignal,
+    stretcher::{check_input, check_ratio, stretch_and_resample, FrameConfig, StretchConfig, StretchError, TimeStretcher},
+    windows::{build_window, WindowFn},
+};
 
 /// Synchronized Overlap Add
 pub fn sola<T, F>(
@@ -9,18 +16,23 @@ pub fn sola<T, F>(
     window_size: usize,
     hop_length: usize,
     window_fn: F,
-) -> TimeDomainSignal<T>
+    monitor: &Monitor,
+) -> Result<TimeDomainSignal<T>, StretchError>
 where
-    T: AudioSample + std::iter::Sum,
+    T: AudioSample,
     F: Fn(f32, usize) -> T
 {
-    let synth_hop_length = (hop_length as f32 * scale_factor) as usize;
-    let synth_len = (signal.len() as f32 / hop_length as f32).ceil() as usize * synth_hop_length + window_size;
+    FrameConfig::new(window_size, hop_length)?;
+    check_ratio("scale factor", scale_factor)?;
+    check_input(&signal)?;
+
+    let synth_len = (signal.len() as f32 * scale_factor).ceil() as usize + window_size;
     let mut synth_signal = TimeDomainSignal::from_elem(synth_len, T::zero());
     let mut synth_norm_signal = TimeDomainSignal::from_elem(synth_len, T::zero());
     let mut synth_weights = TimeDomainSignal::from_elem(synth_len, T::zero());
 
     let window_f = build_window(window_fn, window_size);
+    let frames = signal.len().div_ceil(hop_length);
 
     let mut last_index = 0;
     for i in (0..signal.len()).step_by(hop_length) {
@@ -40,10 +52,6 @@ where
             for j in idx..(idx + window_size / 5).min(synth_len - len).min(idx + overlap) {
                 let area = -(&window.slice(s![..overlap]) * &synth_norm_signal.slice(s![j..j + overlap])).sum();
 
-                if j >= synth_len - len {
-                    dbg!(j, synth_len - len);
-                }
-
                 if area < min_area {
                     min_area = area;
                     index = j;
@@ -66,7 +74,108 @@ where
             .assign(&(&synth_signal.slice(s![index..index + len]) / window_weights.mapv(|v| if v == T::zero() { T::one() } else { v })));
 
         last_index = index;
+
+        monitor.frame(Stage::Sola, i / hop_length + 1, frames)?;
+    }
+
+    Ok(synth_norm_signal)
+}
+
+/// Synchronized Overlap Add, splicing unwindowed segments together with an explicit `crossfade`.
+///
+/// Each segment is shifted forward by up to `search_length` samples to the position where its
+/// start correlates best with the signal synthesized so far, then crossfaded into place.
+pub fn sola_crossfade<T>(
+    signal: TimeDomainSignal<T>,
+    scale_factor: f32,
+    hop_length: usize,
+    search_length: usize,
+    crossfade: Crossfade,
+    monitor: &Monitor,
+) -> Result<TimeDomainSignal<T>, StretchError>
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    if hop_length == 0 {
+        return Err(StretchError::ZeroHop);
     }
+    check_ratio("scale factor", scale_factor)?;
+    check_input(&signal)?;
+
+    let synth_hop_length = ((hop_length as f32 * scale_factor) as usize).max(1);
+    let segment_len = synth_hop_length + crossfade.overlap + search_length;
+    let synth_len = (signal.len() as f32 * scale_factor).ceil() as usize + segment_len + search_length;
+    let mut synth_signal = TimeDomainSignal::from_elem(synth_len, T::zero());
+
+    let frames = signal.len().div_ceil(hop_length);
+    let mut written = 0;
+    for i in (0..signal.len()).step_by(hop_length) {
+        let len = segment_len.min(signal.len() - i);
+        let mut index = (i as f32 * scale_factor) as usize;
+
+        let segment = signal.slice(s![i..i + len]);
+        let overlap = crossfade.overlap.min(len);
+
+        if overlap > 0 && index + overlap <= written {
+            let search_end = (index + search_length).min(written - overlap);
 
-    synth_norm_signal
+            let mut max_correlation = f32::NEG_INFINITY;
+            let start = index;
+            for j in start..=search_end {
+                let r = correlation(synth_signal.slice(s![j..j + overlap]), segment.slice(s![..overlap]));
+
+                if r > max_correlation {
+                    max_correlation = r;
+                    index = j;
+                }
+            }
+        }
+
+        written = splice(synth_signal.view_mut(), segment, index, written, &crossfade);
+
+        monitor.frame(Stage::Sola, i / hop_length + 1, frames)?;
+    }
+
+    Ok(synth_signal.slice_move(s![..written]))
+}
+
+/// [`sola`] and [`sola_crossfade`] as a [`TimeStretcher`].
+#[derive(Debug, Clone)]
+pub struct Sola {
+    pub frames: FrameConfig,
+    pub window_fn: WindowFn,
+    /// Splice segments together with this crossfade instead of normalizing by the summed window
+    /// weights, searching for the best splice point over a fifth of the window size.
+    pub crossfade: Option<Crossfade>,
+}
+
+impl TimeStretcher for Sola {
+    fn process_monitored(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<TimeDomainSignal<f32>, StretchError> {
+        self.process_measured(signal, config, monitor).map(|(processed, _)| processed)
+    }
+
+    fn process_measured(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<(TimeDomainSignal<f32>, usize), StretchError> {
+        let output_len = self.output_len(signal.len(), config);
+        let (window_size, hop_length) = (self.frames.window_size(), self.frames.hop_length());
+
+        stretch_and_resample(signal, config, output_len, |signal, scale_factor| match self.crossfade {
+            Some(crossfade) => sola_crossfade(signal, scale_factor, hop_length, window_size / 5, crossfade, monitor),
+            None => sola(signal, scale_factor, window_size, hop_length, self.window_fn, monitor),
+        })
+    }
+
+    fn latency(&self) -> usize {
+        self.frames.window_size()
+    }
 }
@@ -0,0 +1,366 @@
+use std::collections::VecDeque;
+
+use num_complex::{Complex, ComplexFloat};
+
+use crate::{
+    fft::FftPlan,
+    stretcher::{check_ratio, FrameConfig, StretchError},
+    windows::{build_window, WindowFn},
+};
+
+/// A block-based [`phase_vocoder`](crate::phase_vocoder::phase_vocoder) which only keeps the
+/// frames in flight, so that signals of any length can be stretched with input and output
+/// delivered in arbitrary block sizes.
+///
+/// Feed input with [`push`](Self::push), call [`finish`](Self::finish) once the input has
+/// ended, and collect output with [`pull`](Self::pull) whenever convenient.
+pub struct StreamingPhaseVocoder {
+    frames: FrameConfig,
+    /// Kept in double precision, since frame positions and lengths are counted in samples from
+    /// the start of the stream.
+    scale_factor: f64,
+    transient_cutoff: f32,
+    window: Vec<f32>,
+
+    fft: FftPlan<f32>,
+    buffer: Vec<Complex<f32>>,
+
+    /// Input samples starting at the absolute index `input_offset`.
+    input: VecDeque<f32>,
+    input_offset: usize,
+    input_len: usize,
+    finished: bool,
+
+    /// Analysis frames starting at the absolute frame `analysis_offset`.
+    analysis: VecDeque<AnalysisFrame>,
+    analysis_offset: usize,
+    last_phases: Vec<f32>,
+    spare_frames: Vec<AnalysisFrame>,
+
+    /// The next synthesis frame, with the magnitudes and phases of the previous one.
+    synth_frame: usize,
+    synth_mags: Vec<f32>,
+    synth_phases: Vec<f32>,
+
+    /// Overlap-added output samples and window weights starting at the absolute index
+    /// `output_offset`.
+    output: VecDeque<f32>,
+    weights: VecDeque<f32>,
+    output_offset: usize,
+    ready: VecDeque<f32>,
+}
+
+/// The magnitudes, phases and phase differences of one frame of the input's STFT.
+struct AnalysisFrame {
+    mags: Vec<f32>,
+    phases: Vec<f32>,
+    phase_diffs: Vec<f32>,
+}
+
+impl AnalysisFrame {
+    fn new(window_size: usize) -> Self {
+        Self {
+            mags: vec![0.0; window_size],
+            phases: vec![0.0; window_size],
+            phase_diffs: vec![0.0; window_size],
+        }
+    }
+}
+
+impl StreamingPhaseVocoder {
+    pub fn new(
+        scale_factor: f32,
+        frames: FrameConfig,
+        transient_cutoff: f32,
+        window_fn: WindowFn,
+    ) -> Result<Self, StretchError> {
+        check_ratio("scale factor", scale_factor)?;
+
+        let window_size = frames.window_size();
+
+        Ok(Self {
+            frames,
+            scale_factor: scale_factor as f64,
+            transient_cutoff,
+            window: build_window(window_fn, window_size).to_vec(),
+            fft: FftPlan::new(window_size),
+            buffer: vec![Complex::default(); window_size],
+            input: VecDeque::new(),
+            input_offset: 0,
+            input_len: 0,
+            finished: false,
+            analysis: VecDeque::new(),
+            analysis_offset: 0,
+            last_phases: vec![0.0; window_size],
+            spare_frames: Vec::new(),
+            synth_frame: 0,
+            synth_mags: vec![0.0; window_size],
+            synth_phases: vec![0.0; window_size],
+            output: VecDeque::new(),
+            weights: VecDeque::new(),
+            output_offset: 0,
+            ready: VecDeque::new(),
+        })
+    }
+
+    /// Preallocate every buffer so that pushing blocks of up to `max_block_size` samples and
+    /// pulling all available output after each push never allocates.
+    pub fn reserve(&mut self, max_block_size: usize) {
+        let window_size = self.frames.window_size();
+        let hop_length = self.frames.hop_length();
+
+        self.input.reserve(window_size + hop_length + max_block_size);
+        self.output.reserve(window_size + hop_length);
+        self.weights.reserve(window_size + hop_length);
+        self.ready.reserve((max_block_size as f64 * self.scale_factor).ceil() as usize + window_size + 2 * hop_length);
+
+        // Synthesis keeps the frames from `t / scale_factor` up to the newest analysis frame.
+        let frames = (1.0 / self.scale_factor).ceil() as usize + 4;
+        self.analysis.reserve(frames);
+        while self.spare_frames.len() + self.analysis.len() < frames {
+            self.spare_frames.push(AnalysisFrame::new(window_size));
+        }
+    }
+
+    /// Clear all input and output, returning to the state right after construction while
+    /// keeping every allocation.
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.input_offset = 0;
+        self.input_len = 0;
+        self.finished = false;
+
+        self.spare_frames.extend(self.analysis.drain(..));
+        self.analysis_offset = 0;
+        self.last_phases.fill(0.0);
+
+        self.synth_frame = 0;
+        self.synth_mags.fill(0.0);
+        self.synth_phases.fill(0.0);
+
+        self.output.clear();
+        self.weights.clear();
+        self.output_offset = 0;
+        self.ready.clear();
+    }
+
+    /// Append a block of input samples, which fails with [`StretchError::Finished`] after
+    /// [`finish`](Self::finish).
+    pub fn push(&mut self, input: &[f32]) -> Result<(), StretchError> {
+        if self.finished {
+            return Err(StretchError::Finished);
+        }
+
+        self.input.extend(input);
+        self.input_len += input.len();
+        self.process();
+        Ok(())
+    }
+
+    /// Mark the end of the input, making the remaining output available.
+    pub fn finish(&mut self) {
+        self.finished = true;
+        self.process();
+    }
+
+    /// Move up to `output.len()` processed samples into `output`, returning how many were written.
+    pub fn pull(&mut self, output: &mut [f32]) -> usize {
+        let len = output.len().min(self.ready.len());
+
+        for (out, sample) in output.iter_mut().zip(self.ready.drain(..len)) {
+            *out = sample;
+        }
+
+        len
+    }
+
+    /// The number of processed samples that can be pulled.
+    pub fn available(&self) -> usize {
+        self.ready.len()
+    }
+
+    /// Whether the input has finished and every output sample has been pulled.
+    pub fn is_done(&self) -> bool {
+        self.finished && self.ready.is_empty() && self.output_offset >= self.output_len()
+    }
+
+    /// The total number of output samples, once [`finish`](Self::finish) has been called.
+    fn output_len(&self) -> usize {
+        if self.finished {
+            (self.input_len as f64 * self.scale_factor).round() as usize
+        } else {
+            (self.input_len as f64 * self.scale_factor).floor() as usize
+        }
+    }
+
+    /// The number of analysis frames, once [`finish`](Self::finish) has been called.
+    fn total_frames(&self) -> Option<usize> {
+        self.finished.then(|| self.frames.frames(self.input_len))
+    }
+
+    fn process(&mut self) {
+        // Interleave analysis and synthesis so that only the frames in flight are kept.
+        loop {
+            while self.synthesize_frame() {
+                self.complete_output();
+            }
+
+            if !self.analyze_frame() {
+                break;
+            }
+        }
+
+        self.complete_output();
+    }
+
+    /// Move the completed output samples to the ready queue.
+    fn complete_output(&mut self) {
+        // Every sample before the start of the next synthesis frame is complete.
+        let complete = match self.total_frames() {
+            Some(frames) if self.synth_frame >= self.synth_frames(frames) => usize::MAX,
+            _ => self.synth_frame * self.frames.hop_length(),
+        };
+        let end = complete.min(self.output_len());
+
+        while self.output_offset < end {
+            let sample = self.output.pop_front().unwrap_or(0.0);
+            let weight = self.weights.pop_front().unwrap_or(0.0);
+
+            self.ready.push_back(if weight == 0.0 { sample } else { sample / weight });
+            self.output_offset += 1;
+        }
+    }
+
+    fn synth_frames(&self, frames: usize) -> usize {
+        (frames as f64 * self.scale_factor).ceil() as usize
+    }
+
+    /// Compute the next analysis frame if enough input is available.
+    fn analyze_frame(&mut self) -> bool {
+        let window_size = self.frames.window_size();
+        let hop_length = self.frames.hop_length();
+
+        let frame = self.analysis_offset + self.analysis.len();
+        let start = frame * hop_length;
+
+        let available = if self.finished {
+            start < self.input_len
+        } else {
+            start + window_size <= self.input_len
+        };
+
+        if !available {
+            return false;
+        }
+
+        for (n, c) in self.buffer.iter_mut().enumerate() {
+            let sample = self.input.get(start + n - self.input_offset).copied().unwrap_or(0.0);
+            *c = Complex { re: sample * self.window[n], im: 0.0 };
+        }
+
+        self.fft.forward(&mut self.buffer);
+
+        let mut analysis = self.spare_frames.pop().unwrap_or_else(|| AnalysisFrame::new(window_size));
+        for (k, c) in self.buffer.iter().enumerate() {
+            let phase = f32::atan2(c.im, c.re);
+
+            analysis.mags[k] = c.abs();
+            analysis.phases[k] = phase;
+            analysis.phase_diffs[k] = (phase - self.last_phases[k]).rem_euclid(std::f32::consts::TAU);
+            self.last_phases[k] = phase;
+        }
+
+        self.analysis.push_back(analysis);
+
+        // Discard the input that no later frame will read.
+        let next_start = (frame + 1) * hop_length;
+        let discard = next_start.saturating_sub(self.input_offset).min(self.input.len());
+        self.input.drain(..discard);
+        self.input_offset += discard;
+
+        true
+    }
+
+    /// Compute the next synthesis frame if the analysis frames it depends on are available.
+    fn synthesize_frame(&mut self) -> bool {
+        let window_size = self.frames.window_size();
+        let hop_length = self.frames.hop_length();
+        let total_frames = self.total_frames();
+
+        if let Some(frames) = total_frames {
+            if frames == 0 || self.synth_frame >= self.synth_frames(frames) {
+                return false;
+            }
+        }
+
+        let t = self.synth_frame;
+        let index = t as f64 / self.scale_factor;
+        let last = total_frames.map_or(usize::MAX, |frames| frames - 1);
+
+        let i0 = index.floor() as usize;
+        let i1 = index.ceil() as usize;
+        let d0 = (index - index.floor()) as f32;
+        let d1 = if i0 == i1 { 1.0 } else { (index.ceil() - index) as f32 };
+        let (i0, i1) = (i0.min(last), i1.min(last));
+        let nearest = (index.round() as usize).min(last);
+
+        if i1.max(nearest) >= self.analysis_offset + self.analysis.len() {
+            return false;
+        }
+
+        let frame0 = &self.analysis[i0 - self.analysis_offset];
+        let frame1 = &self.analysis[i1 - self.analysis_offset];
+        let frame_nearest = &self.analysis[nearest - self.analysis_offset];
+
+        for k in 0..window_size {
+            let mag = frame0.mags[k] * (1.0 - d0) + frame1.mags[k] * (1.0 - d1);
+            let phase_diff = frame0.phase_diffs[k] * (1.0 - d0) + frame1.phase_diffs[k] * (1.0 - d1);
+
+            let phase = if t == 0 {
+                phase_diff
+            } else {
+                let prev_mag = self.synth_mags[k];
+                let transient = (mag - prev_mag) / (mag + prev_mag);
+
+                let phase = if transient >= self.transient_cutoff {
+                    frame_nearest.phases[k]
+                } else {
+                    self.synth_phases[k] + phase_diff
+                };
+                phase.rem_euclid(std::f32::consts::TAU)
+            };
+
+            self.synth_mags[k] = mag;
+            self.synth_phases[k] = phase;
+            self.buffer[k] = Complex::from_polar(mag, phase);
+        }
+
+        self.fft.inverse(&mut self.buffer);
+
+        let start = t * hop_length - self.output_offset;
+        if self.output.len() < start + window_size {
+            self.output.resize(start + window_size, 0.0);
+            self.weights.resize(start + window_size, 0.0);
+        }
+
+        for n in 0..window_size {
+            self.output[start + n] += self.buffer[n].re * self.window[n];
+            self.weights[start + n] += self.window[n];
+        }
+
+        self.synth_frame += 1;
+
+        // Discard the analysis frames that no later synthesis frame will read.
+        let first_needed = ((self.synth_frame as f64 / self.scale_factor).floor() as usize).min(last);
+        while self.analysis_offset < first_needed {
+            let Some(frame) = self.analysis.pop_front() else {
+                break;
+            };
+
+            self.spare_frames.push(frame);
+            self.analysis_offset += 1;
+        }
+
+        true
+    }
+}
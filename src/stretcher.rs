@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    progress::Monitor,
+    resample::resample_linear,
+    signal::{Signal, TimeDomainSignal},
+};
+
+/// The smallest window size accepted by the algorithms, in samples.
+pub const MIN_WINDOW_SIZE: usize = 4;
+
+/// The largest window size accepted by the algorithms, in samples.
+pub const MAX_WINDOW_SIZE: usize = 1 << 20;
+
+/// How a [`TimeStretcher`] should change the duration and pitch of a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StretchConfig {
+    /// The ratio of the output duration to the input duration.
+    pub stretch: f32,
+    /// The ratio of the output pitch to the input pitch.
+    pub pitch: f32,
+}
+
+impl StretchConfig {
+    /// Change the duration by `stretch` while keeping the pitch.
+    pub fn stretch(stretch: f32) -> Self {
+        Self { stretch, pitch: 1.0 }
+    }
+
+    /// Change the pitch by `pitch` while keeping the duration.
+    pub fn shift(pitch: f32) -> Self {
+        Self { stretch: 1.0, pitch }
+    }
+
+    /// Check that both ratios are finite and positive.
+    pub fn validate(&self) -> Result<(), StretchError> {
+        check_ratio("stretch", self.stretch)?;
+        check_ratio("pitch", self.pitch)?;
+        Ok(())
+    }
+}
+
+impl Default for StretchConfig {
+    fn default() -> Self {
+        Self { stretch: 1.0, pitch: 1.0 }
+    }
+}
+
+/// The window size and hop length used to split a signal into frames, validated so that the
+/// hop length is non-zero and no larger than the window size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameConfig {
+    window_size: usize,
+    hop_length: usize,
+}
+
+impl FrameConfig {
+    pub fn new(window_size: usize, hop_length: usize) -> Result<Self, StretchError> {
+        if !(MIN_WINDOW_SIZE..=MAX_WINDOW_SIZE).contains(&window_size) {
+            return Err(StretchError::WindowSize { window_size });
+        }
+
+        if hop_length == 0 {
+            return Err(StretchError::ZeroHop);
+        }
+
+        if hop_length > window_size {
+            return Err(StretchError::HopExceedsWindow { hop_length, window_size });
+        }
+
+        Ok(Self { window_size, hop_length })
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    pub fn hop_length(&self) -> usize {
+        self.hop_length
+    }
+
+    /// The number of frames needed to cover `len` samples.
+    pub fn frames(&self, len: usize) -> usize {
+        len.div_ceil(self.hop_length)
+    }
+}
+
+/// A time stretching and pitch shifting algorithm with its parameters, selectable at runtime
+/// through `Box<dyn TimeStretcher>`.
+pub trait TimeStretcher: Send + Sync {
+    /// Stretch and pitch shift the given `signal`, producing exactly
+    /// [`output_len`](TimeStretcher::output_len) samples.
+    fn process(&self, signal: TimeDomainSignal<f32>, config: &StretchConfig) -> Result<TimeDomainSignal<f32>, StretchError> {
+        self.process_monitored(signal, config, &Monitor::default())
+    }
+
+    /// [`process`](TimeStretcher::process), reporting progress to and checking for cancellation
+    /// with the given `monitor` between frames.
+    fn process_monitored(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<TimeDomainSignal<f32>, StretchError>;
+
+    /// [`process_monitored`](TimeStretcher::process_monitored), also returning the number of
+    /// samples the algorithm produced before its output was padded or truncated to
+    /// [`output_len`](TimeStretcher::output_len), counted in output samples.
+    fn process_measured(
+        &self,
+        signal: TimeDomainSignal<f32>,
+        config: &StretchConfig,
+        monitor: &Monitor,
+    ) -> Result<(TimeDomainSignal<f32>, usize), StretchError> {
+        let processed = self.process_monitored(signal, config, monitor)?;
+        let len = processed.len();
+        Ok((processed, len))
+    }
+
+    /// Stretch and pitch shift every channel of `signal` independently, each on its own thread if
+    /// `parallel` is set, keeping its sample rate, layout and metadata.
+    fn process_channels(
+        &self,
+        signal: Signal<f32>,
+        config: &StretchConfig,
+        parallel: bool,
+    ) -> Result<Signal<f32>, StretchError> {
+        self.process_channels_monitored(signal, config, parallel, &Monitor::default())
+    }
+
+    /// [`process_channels`](TimeStretcher::process_channels), reporting the progress of every
+    /// channel to `monitor`, [tagged](Monitor::with_channel) with its index, and stopping every
+    /// channel once it is cancelled.
+    fn process_channels_monitored(
+        &self,
+        signal: Signal<f32>,
+        config: &StretchConfig,
+        parallel: bool,
+        monitor: &Monitor,
+    ) -> Result<Signal<f32>, StretchError> {
+        if signal.channels() == 0 {
+            return Err(StretchError::EmptyInput);
+        }
+
+        let process = |channel: usize| {
+            self.process_monitored(signal.channel(channel).to_owned(), config, &monitor.with_channel(channel))
+        };
+
+        let channels = if parallel {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..signal.channels())
+                    .map(|channel| scope.spawn(move || process(channel)))
+                    .collect();
+
+                handles.into_iter()
+                    .map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                    .collect::<Result<Vec<_>, _>>()
+            })?
+        } else {
+            (0..signal.channels()).map(process).collect::<Result<Vec<_>, _>>()?
+        };
+
+        let views: Vec<_> = channels.iter().map(|channel| channel.view()).collect();
+        let samples = ndarray::stack(ndarray::Axis(1), &views).expect("every channel is stretched to the same length");
+
+        Ok(signal.with_samples(samples).expect("one output per channel"))
+    }
+
+    /// The number of samples produced when processing `input_len` samples.
+    fn output_len(&self, input_len: usize, config: &StretchConfig) -> usize {
+        (input_len as f32 * config.stretch).round() as usize
+    }
+
+    /// The number of input samples the algorithm must read before it can produce its first
+    /// complete output sample.
+    fn latency(&self) -> usize;
+}
+
+/// Check that the ratio `name` is finite and positive.
+pub fn check_ratio(name: &'static str, value: f32) -> Result<f32, StretchError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(StretchError::InvalidRatio { name, value })
+    }
+}
+
+/// Check that the given input signal is not empty.
+pub fn check_input<T>(signal: &TimeDomainSignal<T>) -> Result<(), StretchError> {
+    if signal.is_empty() {
+        Err(StretchError::EmptyInput)
+    } else {
+        Ok(())
+    }
+}
+
+/// Pitch shift with an algorithm that can only change the duration of a signal, by stretching
+/// it by `config.stretch * config.pitch` and then resampling it to `output_len` samples.
+///
+/// Also returns the length the algorithm produced before it was fitted, scaled to output samples.
+pub(crate) fn stretch_and_resample<F>(
+    signal: TimeDomainSignal<f32>,
+    config: &StretchConfig,
+    output_len: usize,
+    stretch: F,
+) -> Result<(TimeDomainSignal<f32>, usize), StretchError>
+where
+    F: FnOnce(TimeDomainSignal<f32>, f32) -> Result<TimeDomainSignal<f32>, StretchError>,
+{
+    config.validate()?;
+
+    let scale_factor = config.stretch * config.pitch;
+    let stretched_len = (signal.len() as f32 * scale_factor).round() as usize;
+
+    let mut stretched = stretch(signal, scale_factor)?;
+    let produced_len = match stretched_len {
+        0 => stretched.len(),
+        _ => (stretched.len() as f64 * output_len as f64 / stretched_len as f64).round() as usize,
+    };
+    fit(&mut stretched, stretched_len);
+
+    if stretched_len == output_len {
+        Ok((stretched, produced_len))
+    } else {
+        Ok((resample_linear(&stretched, output_len), produced_len))
+    }
+}
+
+/// Truncate or zero-pad `signal` to exactly `len` samples.
+pub(crate) fn fit(signal: &mut TimeDomainSignal<f32>, len: usize) {
+    if signal.len() > len {
+        signal.slice_collapse(ndarray::s![..len]);
+    } else if signal.len() < len {
+        let padding = TimeDomainSignal::zeros(len - signal.len());
+        let _ = signal.append(ndarray::Axis(0), padding.view());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum StretchError {
+    #[error("the {name} ratio must be finite and positive, got {value}")]
+    InvalidRatio { name: &'static str, value: f32 },
+    #[error("the window size must be between {MIN_WINDOW_SIZE} and {MAX_WINDOW_SIZE} samples, got {window_size}")]
+    WindowSize { window_size: usize },
+    #[error("the hop length must be at least one sample")]
+    ZeroHop,
+    #[error("the hop length ({hop_length}) must not exceed the window size ({window_size})")]
+    HopExceedsWindow { hop_length: usize, window_size: usize },
+    #[error("the input signal is empty")]
+    EmptyInput,
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(String),
+    #[error("the render was cancelled")]
+    Cancelled,
+    #[error("input was pushed after the stream finished")]
+    Finished,
+    #[error("the processor was used before it was prepared")]
+    NotPrepared,
+    #[error("the input block has {input} samples but the output block has {output}")]
+    BlockMismatch { input: usize, output: usize },
+    #[error("the block of {len} samples exceeds the prepared maximum of {max_block_size}")]
+    BlockTooLong { len: usize, max_block_size: usize },
+}
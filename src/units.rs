@@ -0,0 +1,205 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::stretcher::{FrameConfig, StretchError};
+
+/// A length given either in samples or as a duration, resolved against a sample rate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Length {
+    Samples(usize),
+    Ms(f32),
+    Seconds(f32),
+}
+
+impl Length {
+    /// Convert to a number of samples at the given `sample_rate`.
+    pub fn to_samples(self, sample_rate: u32) -> usize {
+        match self {
+            Length::Samples(samples) => samples,
+            Length::Ms(ms) => (sample_rate as f32 * ms / 1000.0).round() as usize,
+            Length::Seconds(seconds) => (sample_rate as f32 * seconds).round() as usize,
+        }
+    }
+
+    /// Whether the length is given as a duration rather than in samples.
+    pub fn is_duration(self) -> bool {
+        !matches!(self, Length::Samples(_))
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Samples(samples) => write!(f, "{samples}"),
+            Length::Ms(ms) => write!(f, "{ms}ms"),
+            Length::Seconds(seconds) => write!(f, "{seconds}s"),
+        }
+    }
+}
+
+/// Parse a number of samples (`4096`), milliseconds (`20ms`) or seconds (`0.5s`).
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid length `{s}`, expected samples (4096), milliseconds (20ms) or seconds (0.5s)");
+
+        let duration = |v: &str| v.trim().parse::<f32>().ok().filter(|v| v.is_finite() && *v >= 0.0);
+
+        if let Some(samples) = s.strip_suffix("samples") {
+            samples.trim().parse().map(Length::Samples).map_err(|_| invalid())
+        } else if let Some(ms) = s.strip_suffix("ms") {
+            duration(ms).map(Length::Ms).ok_or_else(invalid)
+        } else if let Some(seconds) = s.strip_suffix('s') {
+            duration(seconds).map(Length::Seconds).ok_or_else(invalid)
+        } else {
+            s.parse().map(Length::Samples).map_err(|_| invalid())
+        }
+    }
+}
+
+/// The distance between consecutive frames, given as a [`Length`] or as the percentage by which
+/// consecutive windows overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Hop {
+    Overlap { overlap: f32 },
+    Length(Length),
+}
+
+impl Hop {
+    /// Convert to a number of samples at the given `sample_rate`, for windows of `window_size`
+    /// samples.
+    pub fn to_samples(self, sample_rate: u32, window_size: usize) -> Result<usize, StretchError> {
+        match self {
+            Hop::Length(length) => Ok(length.to_samples(sample_rate)),
+            Hop::Overlap { overlap } => {
+                if !(0.0..100.0).contains(&overlap) {
+                    return Err(StretchError::InvalidParameter(format!(
+                        "the overlap must be at least 0% and below 100%, got {overlap}%",
+                    )));
+                }
+
+                Ok(((window_size as f32 * (1.0 - overlap / 100.0)).round() as usize).max(1))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Hop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hop::Overlap { overlap } => write!(f, "{overlap}%"),
+            Hop::Length(length) => length.fmt(f),
+        }
+    }
+}
+
+/// Parse a [`Length`] or an overlap percentage (`75%`).
+impl FromStr for Hop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().strip_suffix('%') {
+            Some(overlap) => overlap.trim().parse()
+                .map(|overlap| Hop::Overlap { overlap })
+                .map_err(|_| format!("invalid overlap `{s}`")),
+            None => s.parse().map(Hop::Length),
+        }
+    }
+}
+
+impl From<Length> for Hop {
+    fn from(length: Length) -> Self {
+        Hop::Length(length)
+    }
+}
+
+/// How a window size is rounded to a size the FFT can process efficiently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FftRounding {
+    /// Keep sizes given in samples and round sizes given as durations to the nearest
+    /// [`Efficient`](FftRounding::Efficient) size.
+    #[default]
+    Auto,
+    /// Never round.
+    Exact,
+    /// Round to the nearest power of two.
+    PowerOfTwo,
+    /// Round to the nearest size whose only prime factors are 2, 3 and 5.
+    Efficient,
+}
+
+impl FftRounding {
+    pub fn round(self, size: usize, length: Length) -> usize {
+        match self {
+            FftRounding::Auto if length.is_duration() => efficient_fft_size(size),
+            FftRounding::Auto | FftRounding::Exact => size,
+            FftRounding::PowerOfTwo => nearest_power_of_two(size),
+            FftRounding::Efficient => efficient_fft_size(size),
+        }
+    }
+}
+
+/// A window size and hop length in any unit, resolved to a [`FrameConfig`] against the sample
+/// rate of the signal being processed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameSpec {
+    pub window_size: Length,
+    pub hop_length: Hop,
+    #[serde(default)]
+    pub rounding: FftRounding,
+}
+
+impl FrameSpec {
+    pub fn new(window_size: Length, hop_length: impl Into<Hop>) -> Self {
+        Self { window_size, hop_length: hop_length.into(), rounding: FftRounding::Auto }
+    }
+
+    pub fn with_rounding(mut self, rounding: FftRounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Convert to a number of samples at the given `sample_rate`, rounding the window size first
+    /// so that an overlap is relative to the rounded size.
+    pub fn resolve(&self, sample_rate: u32) -> Result<FrameConfig, StretchError> {
+        let window_size = self.rounding.round(self.window_size.to_samples(sample_rate), self.window_size);
+        let hop_length = self.hop_length.to_samples(sample_rate, window_size)?;
+
+        FrameConfig::new(window_size, hop_length)
+    }
+}
+
+/// Find the power of two closest to `size`.
+pub fn nearest_power_of_two(size: usize) -> usize {
+    let upper = size.max(1).next_power_of_two();
+    let lower = upper / 2;
+
+    if lower > 0 && size - lower < upper - size { lower } else { upper }
+}
+
+/// Find the size closest to `size` whose only prime factors are 2, 3 and 5, preferring the larger
+/// size on ties.
+pub fn efficient_fft_size(size: usize) -> usize {
+    let size = size.max(1);
+
+    (0..size)
+        .flat_map(|d| [size + d, size - d])
+        .find(|&n| n > 0 && is_efficient_fft_size(n))
+        .unwrap_or(1)
+}
+
+fn is_efficient_fft_size(mut n: usize) -> bool {
+    for p in [2, 3, 5] {
+        while n.is_multiple_of(p) {
+            n /= p;
+        }
+    }
+
+    n == 1
+}
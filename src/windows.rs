@@ -1,7 +1,13 @@
+use std::{fmt, str::FromStr};
+
 use ndarray::Array1;
+use serde::{Deserialize, Serialize};
 
 use crate::sample::{AudioSample, ConvertSample};
 
+/// A window function evaluated at offset `x` from the center of a window of `window_size` samples.
+pub type WindowFn = fn(f32, usize) -> f32;
+
 pub fn build_window<T, F>(f: F, window_size: usize) -> Array1<T>
 where 
     T: AudioSample,
@@ -30,3 +36,99 @@ where
     let cosx = f32::cos(std::f32::consts::PI * x * inv_win_size);
     (inv_win_size * cosx * cosx).convert_sample()
 }
+
+pub fn hamming_window<T>(x: f32, window_size: usize) -> T
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    let inv_win_size = 1.0 / window_size as f32;
+    let cosx = f32::cos(std::f32::consts::TAU * x * inv_win_size);
+    (inv_win_size * (0.54 + 0.46 * cosx)).convert_sample()
+}
+
+pub fn blackman_window<T>(x: f32, window_size: usize) -> T
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    let inv_win_size = 1.0 / window_size as f32;
+    let cosx = f32::cos(std::f32::consts::TAU * x * inv_win_size);
+    let cos2x = f32::cos(2.0 * std::f32::consts::TAU * x * inv_win_size);
+    (inv_win_size * (0.42 + 0.5 * cosx + 0.08 * cos2x)).convert_sample()
+}
+
+pub fn triangular_window<T>(x: f32, window_size: usize) -> T
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    let inv_win_size = 1.0 / window_size as f32;
+    (inv_win_size * (1.0 - f32::abs(2.0 * x * inv_win_size))).convert_sample()
+}
+
+pub fn rectangular_window<T>(_x: f32, window_size: usize) -> T
+where
+    T: AudioSample,
+    f32: ConvertSample<T>,
+{
+    (1.0 / window_size as f32).convert_sample()
+}
+
+/// A window function selected by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowFunction {
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    Triangular,
+    Rectangular,
+}
+
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 5] = [
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+        WindowFunction::Triangular,
+        WindowFunction::Rectangular,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowFunction::Hann => "hann",
+            WindowFunction::Hamming => "hamming",
+            WindowFunction::Blackman => "blackman",
+            WindowFunction::Triangular => "triangular",
+            WindowFunction::Rectangular => "rectangular",
+        }
+    }
+
+    pub fn function(self) -> WindowFn {
+        match self {
+            WindowFunction::Hann => hann_window,
+            WindowFunction::Hamming => hamming_window,
+            WindowFunction::Blackman => blackman_window,
+            WindowFunction::Triangular => triangular_window,
+            WindowFunction::Rectangular => rectangular_window,
+        }
+    }
+}
+
+impl fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WindowFunction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WindowFunction::ALL.into_iter()
+            .find(|w| w.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown window function `{s}`"))
+    }
+}